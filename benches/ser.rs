@@ -0,0 +1,96 @@
+//! Compares `ser::to_writer` with a hand-written encoder which formats every
+//! header with `format!`, as the serializer used to. That the serializer
+//! doesn't allocate is checked by the `ser_no_allocation` test.
+//!
+//! Run with `cargo bench --bench ser`.
+
+use resp_protocol::{to_writer, RESPType};
+use std::hint::black_box;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 200_000;
+
+/// Encoder as it was before headers were formatted on the stack.
+fn to_writer_format<W: Write>(writer: &mut W, value: &RESPType) {
+    match value {
+        RESPType::SimpleString(s) | RESPType::Error(s) => {
+            let prefix = if let RESPType::Error(_) = value {
+                b"-"
+            } else {
+                b"+"
+            };
+            writer.write_all(prefix).unwrap();
+            writer.write_all(s).unwrap();
+            writer.write_all(b"\r\n").unwrap();
+        }
+        RESPType::Integer(v) => {
+            writer.write_all(b":").unwrap();
+            writer.write_all(format!("{}", v).as_bytes()).unwrap();
+            writer.write_all(b"\r\n").unwrap();
+        }
+        RESPType::BulkString(Some(v)) => {
+            writer.write_all(b"$").unwrap();
+            writer
+                .write_all(format!("{}\r\n", v.len() as u64).as_bytes())
+                .unwrap();
+            writer.write_all(v).unwrap();
+            writer.write_all(b"\r\n").unwrap();
+        }
+        RESPType::BulkString(None) => writer.write_all(b"$-1\r\n").unwrap(),
+        RESPType::Array(Some(items)) => {
+            writer
+                .write_all(format!("*{}\r\n", items.len()).as_bytes())
+                .unwrap();
+            for item in items {
+                to_writer_format(writer, item);
+            }
+        }
+        RESPType::Array(None) => writer.write_all(b"*-1\r\n").unwrap(),
+    }
+}
+
+fn bench<F: FnMut(&mut Vec<u8>)>(mut encode: F) -> Duration {
+    let mut buf = Vec::with_capacity(1 << 20);
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        buf.clear();
+        encode(&mut buf);
+        black_box(&buf);
+    }
+    start.elapsed()
+}
+
+fn bulk(bytes: &[u8]) -> RESPType {
+    RESPType::BulkString(Some(bytes.to_vec()))
+}
+
+fn main() {
+    let cases = vec![
+        ("integer", RESPType::Integer(-1_234_567)),
+        (
+            "command",
+            RESPType::Array(Some(vec![
+                bulk(b"SET"),
+                bulk(b"user:1000:session"),
+                bulk(b"0123456789abcdef0123456789abcdef"),
+            ])),
+        ),
+        (
+            "array of 100 integers",
+            RESPType::Array(Some((0..100).map(RESPType::Integer).collect())),
+        ),
+        ("16 KiB bulk string", bulk(&[b'x'; 16 * 1024])),
+    ];
+    for (name, value) in &cases {
+        let format = bench(|buf| to_writer_format(buf, value));
+        let stack = bench(|buf| to_writer(buf, value).unwrap());
+        println!(
+            "{:<24} format!: {:>10.2?}  stack: {:>10.2?}  ({:.2}x)",
+            name,
+            format / ITERATIONS,
+            stack / ITERATIONS,
+            format.as_secs_f64() / stack.as_secs_f64()
+        );
+    }
+}
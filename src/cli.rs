@@ -0,0 +1,269 @@
+use crate::inline::split_args;
+use crate::{Error, RESPType, Result};
+use std::fmt::{self, Display, Formatter, Write};
+
+/// Split a `redis-cli` style line (`HSET "my key" field "\x00\xff"`) into a
+/// command, which is an array of bulk strings.
+///
+/// Quoting rules are the same as for inline commands, see `inline::split_args`.
+pub fn parse_command(line: &str) -> Result<RESPType> {
+    let args = split_args(line.as_bytes())?;
+    Ok(RESPType::Array(Some(
+        args.into_iter()
+            .map(|arg| RESPType::BulkString(Some(arg)))
+            .collect(),
+    )))
+}
+
+/// Write a command back as a line that `parse_command` and `redis-cli` read
+/// as the same command. Arguments are quoted only when it is needed.
+///
+/// Command must be an array of bulk strings, simple strings or integers.
+pub fn format_command(command: &RESPType) -> Result<String> {
+    let args = match command {
+        RESPType::Array(Some(args)) => args,
+        _ => return Err(Error::Message("command must be an array".to_owned())),
+    };
+    let mut line = String::new();
+    for arg in args {
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        match arg {
+            RESPType::BulkString(Some(bytes)) | RESPType::SimpleString(bytes) => {
+                push_arg(&mut line, bytes)
+            }
+            RESPType::Integer(int) => line.push_str(&int.to_string()),
+            _ => {
+                return Err(Error::Message(
+                    "command arguments must be strings or integers".to_owned(),
+                ))
+            }
+        }
+    }
+    Ok(line)
+}
+
+/// Quote bytes like `redis-cli` does (`sdscatrepr`): in double quotes, with
+/// backslash escapes for quotes, control characters and non-ASCII bytes.
+pub fn quote(bytes: &[u8]) -> String {
+    let mut quoted = String::with_capacity(bytes.len() + 2);
+    push_quoted(&mut quoted, bytes);
+    quoted
+}
+
+/// Format a reply the way `redis-cli` prints it:
+///
+/// ```text
+/// 1) (integer) 5
+/// 2) 1) "hello"
+///    2) (nil)
+/// 3) (error) ERR unknown command
+/// ```
+pub fn pretty(value: &RESPType) -> Pretty<'_> {
+    Pretty { value, raw: false }
+}
+
+/// `Display` wrapper returned by `pretty`.
+pub struct Pretty<'a> {
+    value: &'a RESPType,
+    raw: bool,
+}
+
+impl<'a> Pretty<'a> {
+    /// Print like `redis-cli --raw`: strings and errors as they are, `nil` as
+    /// an empty line and array items on separate lines without numbering.
+    /// Bytes that are not valid UTF-8 are replaced with `U+FFFD`.
+    pub fn raw(mut self, raw: bool) -> Self {
+        self.raw = raw;
+        self
+    }
+}
+
+impl Display for Pretty<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.raw {
+            write_raw(f, self.value)
+        } else {
+            write_pretty(f, self.value, &mut String::new())
+        }
+    }
+}
+
+fn write_pretty(f: &mut Formatter<'_>, value: &RESPType, prefix: &mut String) -> fmt::Result {
+    match value {
+        RESPType::SimpleString(s) => f.write_str(&String::from_utf8_lossy(s)),
+        RESPType::Error(err) => write!(f, "(error) {}", String::from_utf8_lossy(err)),
+        RESPType::Integer(int) => write!(f, "(integer) {}", int),
+        RESPType::BulkString(Some(bytes)) => {
+            let mut quoted = String::with_capacity(bytes.len() + 2);
+            push_quoted(&mut quoted, bytes);
+            f.write_str(&quoted)
+        }
+        RESPType::BulkString(None) | RESPType::Array(None) => f.write_str("(nil)"),
+        RESPType::Array(Some(items)) if items.is_empty() => f.write_str("(empty array)"),
+        RESPType::Array(Some(items)) => {
+            // The first item goes on the line of the parent's index, the
+            // others are indented past it.
+            let width = items.len().to_string().len();
+            let indent = prefix.len();
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    write!(f, "\n{}", prefix)?;
+                }
+                write!(f, "{:>width$}) ", i + 1, width = width)?;
+                prefix.push_str(&" ".repeat(width + 2));
+                write_pretty(f, item, prefix)?;
+                prefix.truncate(indent);
+            }
+            Ok(())
+        }
+    }
+}
+
+fn write_raw(f: &mut Formatter<'_>, value: &RESPType) -> fmt::Result {
+    match value {
+        RESPType::SimpleString(s) | RESPType::Error(s) | RESPType::BulkString(Some(s)) => {
+            f.write_str(&String::from_utf8_lossy(s))
+        }
+        RESPType::Integer(int) => write!(f, "{}", int),
+        RESPType::BulkString(None) | RESPType::Array(None) => Ok(()),
+        RESPType::Array(Some(items)) => {
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    f.write_char('\n')?;
+                }
+                write_raw(f, item)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn push_arg(line: &mut String, bytes: &[u8]) {
+    let plain = !bytes.is_empty()
+        && bytes
+            .iter()
+            .all(|&b| b.is_ascii_graphic() && b != b'"' && b != b'\'' && b != b'\\');
+    if plain {
+        line.extend(bytes.iter().map(|&b| b as char));
+    } else {
+        push_quoted(line, bytes);
+    }
+}
+
+pub(crate) fn push_quoted(quoted: &mut String, bytes: &[u8]) {
+    quoted.push('"');
+    for &byte in bytes {
+        match byte {
+            b'\\' => quoted.push_str("\\\\"),
+            b'"' => quoted.push_str("\\\""),
+            b'\n' => quoted.push_str("\\n"),
+            b'\r' => quoted.push_str("\\r"),
+            b'\t' => quoted.push_str("\\t"),
+            0x07 => quoted.push_str("\\a"),
+            0x08 => quoted.push_str("\\b"),
+            b' '..=b'~' => quoted.push(byte as char),
+            _ => quoted.push_str(&format!("\\x{:02x}", byte)),
+        }
+    }
+    quoted.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bulk(bytes: &[u8]) -> RESPType {
+        RESPType::BulkString(Some(bytes.to_vec()))
+    }
+
+    #[test]
+    fn cli_parse_command() {
+        let command = parse_command(r#"HSET "my key" field "\x00\xff""#).unwrap();
+        assert_eq!(
+            command,
+            RESPType::Array(Some(vec![
+                bulk(b"HSET"),
+                bulk(b"my key"),
+                bulk(b"field"),
+                bulk(b"\x00\xff")
+            ]))
+        );
+        assert!(parse_command(r#"GET "key"#).is_err());
+    }
+
+    #[test]
+    fn cli_format_command() {
+        let command = RESPType::Array(Some(vec![
+            bulk(b"SET"),
+            bulk(b"my key"),
+            bulk(b"it's \"\\\"\r\n\t\x07\x08\x00\xff"),
+            bulk(b""),
+            RESPType::Integer(-5),
+        ]));
+        let line = format_command(&command).unwrap();
+        assert_eq!(
+            line,
+            r#"SET "my key" "it's \"\\\"\r\n\t\a\b\x00\xff" "" -5"#
+        );
+        let parsed = parse_command(&line).unwrap();
+        let expected = RESPType::Array(Some(vec![
+            bulk(b"SET"),
+            bulk(b"my key"),
+            bulk(b"it's \"\\\"\r\n\t\x07\x08\x00\xff"),
+            bulk(b""),
+            bulk(b"-5"),
+        ]));
+        assert_eq!(parsed, expected);
+        assert!(format_command(&RESPType::Integer(1)).is_err());
+    }
+
+    #[test]
+    fn cli_quote() {
+        assert_eq!(quote(b"hello"), r#""hello""#);
+        assert_eq!(quote(b"a\"b\x01"), r#""a\"b\x01""#);
+    }
+
+    #[test]
+    fn cli_pretty() {
+        let items: Vec<RESPType> = (1..=10).map(RESPType::Integer).collect();
+        let reply = RESPType::Array(Some(vec![
+            RESPType::SimpleString(b"OK".to_vec()),
+            RESPType::Array(Some(vec![bulk(b"hello\n\xff"), RESPType::BulkString(None)])),
+            RESPType::Error(b"ERR unknown command".to_vec()),
+            RESPType::Array(Some(vec![])),
+            RESPType::Array(Some(items)),
+        ]));
+        let expected = [
+            "1) OK",
+            "2) 1) \"hello\\n\\xff\"",
+            "   2) (nil)",
+            "3) (error) ERR unknown command",
+            "4) (empty array)",
+            "5)  1) (integer) 1",
+            "    2) (integer) 2",
+            "    3) (integer) 3",
+            "    4) (integer) 4",
+            "    5) (integer) 5",
+            "    6) (integer) 6",
+            "    7) (integer) 7",
+            "    8) (integer) 8",
+            "    9) (integer) 9",
+            "   10) (integer) 10",
+        ];
+        assert_eq!(pretty(&reply).to_string(), expected.join("\n"));
+        assert_eq!(pretty(&RESPType::BulkString(None)).to_string(), "(nil)");
+    }
+
+    #[test]
+    fn cli_pretty_raw() {
+        let reply = RESPType::Array(Some(vec![
+            bulk(b"a b"),
+            RESPType::Integer(5),
+            RESPType::BulkString(None),
+            RESPType::Array(Some(vec![RESPType::Error(b"ERR x".to_vec())])),
+        ]));
+        assert_eq!(pretty(&reply).raw(true).to_string(), "a b\n5\n\nERR x");
+    }
+}
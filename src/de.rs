@@ -1,10 +1,20 @@
-use serde::de::{self, DeserializeOwned, DeserializeSeed, SeqAccess, Visitor};
+use serde::de::value::{SeqAccessDeserializer, SeqDeserializer, U32Deserializer};
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, SeqAccess, Unexpected,
+    VariantAccess, Visitor,
+};
+use serde::forward_to_deserialize_any;
 
-use crate::error::{Error, Result};
+use crate::error::{Error, Position, Result, Violation};
+use crate::events::Handler;
+use crate::inline::split_args;
 use crate::RESPType;
+use memchr::memchr;
+use std::convert::TryInto;
 use std::fmt;
-use std::io::{BufRead, BufReader, Cursor};
+use std::io::{self, BufRead, BufReader, Cursor, Read};
 use std::option::Option::None;
+use std::sync::Arc;
 
 pub fn from_string<T>(s: String) -> Result<T>
 where
@@ -24,141 +34,797 @@ where
     Ok(result)
 }
 
+/// Newtype struct name used by `RESPType` to ask for the exact type of the
+/// next value. It is handed over as an enum variant with one of the indexes below.
+pub(crate) const RESP_TYPE_TOKEN: &str = "$resp::RESPType";
+pub(crate) const SIMPLE_STRING: u32 = 0;
+pub(crate) const ERROR: u32 = 1;
+pub(crate) const INTEGER: u32 = 2;
+pub(crate) const BULK_STRING: u32 = 3;
+pub(crate) const NULL_BULK_STRING: u32 = 4;
+pub(crate) const ARRAY: u32 = 5;
+pub(crate) const NULL_ARRAY: u32 = 6;
+
+/// Default limit of nested arrays, see `Deserializer::max_depth`
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
 pub struct Deserializer<'de, R: BufRead> {
     reader: &'de mut R,
+    strict: bool,
+    inline_commands: bool,
+    max_depth: usize,
+    offset: u64,
+    path: Vec<usize>,
+    line_start: bool,
+    /// Prefix which has been read and put back
+    peeked: Option<u8>,
 }
 
 impl<'de, R: BufRead> Deserializer<'de, R> {
     pub fn from_buf_reader(reader: &'de mut R) -> Self {
-        Deserializer { reader }
+        Deserializer {
+            reader,
+            strict: false,
+            inline_commands: false,
+            max_depth: DEFAULT_MAX_DEPTH,
+            offset: 0,
+            path: Vec::new(),
+            line_start: true,
+            peeked: None,
+        }
+    }
+
+    /// Read requests which don't start with `*` as inline commands
+    /// (`SET key "hello world"`), the way Redis servers do, whatever their
+    /// first byte is. They are handed over as arrays of bulk strings, same as
+    /// commands sent by clients. Empty lines are skipped.
+    ///
+    /// Values nested in arrays are parsed as usual.
+    pub fn inline_commands(mut self, enabled: bool) -> Self {
+        self.inline_commands = enabled;
+        self
+    }
+
+    /// Fail with `Error::DepthLimit` on arrays nested deeper than `depth`
+    /// levels, instead of running out of stack. The default is
+    /// `DEFAULT_MAX_DEPTH`.
+    ///
+    /// `read_value` doesn't recurse, so the limit can be lifted for it.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Start reading a bulk string as a stream, `None` for a null bulk string.
+    /// The returned reader yields exactly the declared number of bytes and
+    /// checks the CRLF after them.
+    ///
+    /// Dropping the reader early skips the rest of the bulk string.
+    pub fn read_bulk_stream(&mut self) -> Result<Option<BulkReader<'_, 'de, R>>> {
+        let len = self.parse_located(|de, prefix| match prefix {
+            b'$' => de.parse_length(),
+            _ => Err(Error::syntax("bulk string", &[prefix])),
+        })?;
+        Ok(len.map(move |len| BulkReader {
+            de: self,
+            len: len as u64,
+            remaining: len as u64,
+            done: false,
+        }))
+    }
+
+    /// Start reading an array element by element, `None` for a null array.
+    /// Elements which are left when the reader is dropped are skipped without
+    /// being stored, so huge arrays can be processed in bounded memory.
+    pub fn read_array_stream(&mut self) -> Result<Option<ArrayReader<'_, 'de, R>>> {
+        let len = self.parse_located(|de, prefix| match prefix {
+            b'*' => de.parse_length(),
+            _ => Err(Error::syntax("array", &[prefix])),
+        })?;
+        Ok(len.map(move |len| ArrayReader {
+            de: self,
+            len,
+            index: 0,
+            failed: false,
+        }))
+    }
+
+    /// Read the next value and report its parts to `handler`, without
+    /// recursion. Bulk strings are copied only if they don't fit in the
+    /// reader's buffer.
+    ///
+    /// Arrays nested deeper than `max_depth` fail with `Error::DepthLimit`, as
+    /// for other ways of reading.
+    pub fn parse_events<H: Handler>(&mut self, handler: &mut H) -> Result<()> {
+        let depth = self.path.len();
+        let result = self.parse_events_iterative(handler);
+        self.path.truncate(depth);
+        result
+    }
+
+    /// Read a `RESPType` without recursion: nested arrays are kept on a
+    /// heap-allocated stack, so their depth is bounded by `max_depth` only.
+    ///
+    /// Note that dropping, comparing and printing `RESPType` are recursive.
+    pub fn read_value(&mut self) -> Result<RESPType> {
+        let depth = self.path.len();
+        let result = self.read_value_iterative();
+        self.path.truncate(depth);
+        result
+    }
+
+    /// Number of bytes consumed from the reader
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Skip input up to the next line which starts with a type prefix, so
+    /// reading can go on after an error. Returns the number of skipped bytes.
+    ///
+    /// Bulk strings may contain anything, so the found line is only a
+    /// plausible start of a value.
+    pub fn resync(&mut self) -> Result<u64> {
+        if self.peeked.is_some() {
+            return Ok(0);
+        }
+        let mut skipped = 0;
+        loop {
+            let buf = self.reader.fill_buf()?;
+            if buf.is_empty() || self.line_start && PREFIXES.contains(&buf[0]) {
+                return Ok(skipped);
+            }
+            let (len, line_start) = match memchr(b'\n', buf) {
+                Some(i) => (i + 1, true),
+                None => (buf.len(), false),
+            };
+            self.reader.consume(len);
+            self.offset += len as u64;
+            self.line_start = line_start;
+            skipped += len as u64;
+        }
+    }
+
+    /// Accept only the exact wire format: lines terminated by CRLF and
+    /// integers and lengths without signs, zeros or whitespace that are not
+    /// needed. Broken rules are reported as `Error::Strict`.
+    pub fn strict(mut self, enabled: bool) -> Self {
+        self.strict = enabled;
+        self
     }
 }
 
 impl<'de, R: BufRead> Deserializer<'de, R> {
-    fn parse_isize(&mut self) -> Result<isize> {
-        let mut buf = String::new();
-        self.reader.read_line(&mut buf)?;
-        let buf = buf.trim_end();
-        match buf.parse::<isize>() {
-            Ok(size) => Ok(size),
-            Err(_) => Err(Error::Syntax),
+    /// Consume a line and hand it to `f` without the trailing CRLF. Lines
+    /// that are in the reader's buffer as a whole are not copied.
+    ///
+    /// Outside of strict mode the line may end with a lone LF.
+    fn with_line<T, F>(&mut self, f: F) -> Result<T>
+    where
+        F: FnOnce(&[u8]) -> Result<T>,
+    {
+        self.with_line_max(usize::MAX, f)
+    }
+
+    /// `with_line` for lines of at most `max_len` bytes with their LF,
+    /// longer ones are rejected before they are buffered
+    fn with_line_max<T, F>(&mut self, max_len: usize, f: F) -> Result<T>
+    where
+        F: FnOnce(&[u8]) -> Result<T>,
+    {
+        let strict = self.strict;
+        let buf = self.reader.fill_buf()?;
+        if let Some(i) = memchr(b'\n', buf) {
+            if i >= max_len {
+                return Err(Error::syntax("shorter line", &buf[..i.min(32)]));
+            }
+            let result = trim_line(&buf[..=i], strict).and_then(f);
+            self.reader.consume(i + 1);
+            self.offset += i as u64 + 1;
+            self.line_start = true;
+            return result;
+        }
+        let mut line = Vec::new();
+        loop {
+            let buf = self.reader.fill_buf()?;
+            if buf.is_empty() {
+                break;
+            }
+            let (len, done) = match memchr(b'\n', buf) {
+                Some(i) => (i + 1, true),
+                None => (buf.len(), false),
+            };
+            if line.len() + len > max_len {
+                line.extend_from_slice(&buf[..len.min(32)]);
+                line.truncate(32);
+                return Err(Error::syntax("shorter line", &line));
+            }
+            line.extend_from_slice(&buf[..len]);
+            self.reader.consume(len);
+            self.offset += len as u64;
+            if done {
+                break;
+            }
+        }
+        self.line_start = line.last() == Some(&b'\n');
+        trim_line(&line, strict).and_then(f)
+    }
+
+    fn read_line(&mut self) -> Result<Vec<u8>> {
+        self.with_line(|line| Ok(line.to_vec()))
+    }
+
+    fn parse_bool(&mut self) -> Result<bool> {
+        self.with_line_max(MAX_HEADER_LEN, parse_boolean)
+    }
+
+    fn parse_int(&mut self) -> Result<i64> {
+        let strict = self.strict;
+        self.with_line_max(MAX_HEADER_LEN, |line| {
+            if strict {
+                check_integer(line)?;
+            }
+            parse_integer(line).ok_or_else(|| Error::syntax("integer", line))
+        })
+    }
+
+    fn read_value_iterative(&mut self) -> Result<RESPType> {
+        // Arrays which are being read, with their lengths
+        let mut arrays: Vec<(Vec<RESPType>, usize)> = Vec::new();
+        loop {
+            let mut value = match self.parse_located(Self::parse_frame)? {
+                Frame::SimpleString(bytes) => RESPType::SimpleString(bytes),
+                Frame::Error(bytes) => RESPType::Error(bytes),
+                Frame::Integer(int) => RESPType::Integer(int),
+                Frame::BulkString(bytes) => RESPType::BulkString(bytes),
+                Frame::Array(Some(len)) if len > 0 => {
+                    // Don't trust the length for preallocation
+                    arrays.push((Vec::with_capacity(len.min(1024)), len));
+                    self.path.push(0);
+                    continue;
+                }
+                Frame::Array(len) => RESPType::Array(len.map(|_| Vec::new())),
+                Frame::Inline(args) => RESPType::Array(Some(
+                    args.into_iter()
+                        .map(|arg| RESPType::BulkString(Some(arg)))
+                        .collect(),
+                )),
+            };
+            // Put the value into its array, and the array into its parent
+            // when it is complete
+            loop {
+                let (items, len) = match arrays.last_mut() {
+                    Some(array) => array,
+                    None => return Ok(value),
+                };
+                items.push(value);
+                if items.len() < *len {
+                    if let Some(index) = self.path.last_mut() {
+                        *index += 1;
+                    }
+                    break;
+                }
+                let (items, _) = arrays.pop().unwrap();
+                self.path.pop();
+                value = RESPType::Array(Some(items));
+            }
+        }
+    }
+
+    fn parse_events_iterative<H: Handler>(&mut self, handler: &mut H) -> Result<()> {
+        // Elements left in the arrays which are being read
+        let mut arrays: Vec<usize> = Vec::new();
+        let mut scratch = Vec::new();
+        loop {
+            if let Some(len) =
+                self.parse_located(|de, prefix| de.emit(prefix, handler, &mut scratch))?
+            {
+                arrays.push(len);
+                self.path.push(0);
+                continue;
+            }
+            loop {
+                let left = match arrays.last_mut() {
+                    Some(left) => left,
+                    None => return Ok(()),
+                };
+                *left -= 1;
+                if *left > 0 {
+                    if let Some(index) = self.path.last_mut() {
+                        *index += 1;
+                    }
+                    break;
+                }
+                arrays.pop();
+                self.path.pop();
+                handler.on_array_end()?;
+            }
+        }
+    }
+
+    /// Report a value to `handler`, except for array elements. Returns the
+    /// number of elements of a non-empty array.
+    fn emit<H: Handler>(
+        &mut self,
+        prefix: u8,
+        handler: &mut H,
+        scratch: &mut Vec<u8>,
+    ) -> Result<Option<usize>> {
+        match prefix {
+            _ if self.is_inline(prefix) => match self.read_inline(prefix)? {
+                Inline::Command(args) => {
+                    handler.on_array_start(args.len())?;
+                    for arg in &args {
+                        handler.on_bulk(arg)?;
+                    }
+                    handler.on_array_end()?;
+                }
+                Inline::Value(prefix) => return self.emit(prefix, handler, scratch),
+            },
+            b'+' => self.with_line(|line| handler.on_simple(line))?,
+            b'-' => self.with_line(|line| handler.on_error(line))?,
+            b':' => handler.on_integer(self.parse_int()?)?,
+            b'#' => handler.on_bool(self.parse_bool()?)?,
+            b'$' => match self.parse_length()? {
+                Some(len) => self.with_bulk(len, scratch, |bytes| handler.on_bulk(bytes))?,
+                None => handler.on_null(false)?,
+            },
+            b'*' => match self.parse_length()? {
+                Some(len) => {
+                    self.check_depth()?;
+                    handler.on_array_start(len)?;
+                    if len > 0 {
+                        return Ok(Some(len));
+                    }
+                    handler.on_array_end()?;
+                }
+                None => handler.on_null(true)?,
+            },
+            _ => return Err(Error::syntax(EXPECTED_PREFIX, &[prefix])),
+        }
+        Ok(None)
+    }
+
+    /// Consume a bulk string payload of `len` bytes and hand it to `f`,
+    /// borrowed from the reader's buffer if it's there as a whole
+    fn with_bulk<F>(&mut self, len: usize, scratch: &mut Vec<u8>, f: F) -> Result<()>
+    where
+        F: FnOnce(&[u8]) -> Result<()>,
+    {
+        let buf = self.reader.fill_buf()?;
+        if buf.len() >= len.saturating_add(2) {
+            let end = &buf[len..len + 2];
+            if end != b"\r\n" {
+                return Err(Error::syntax("CRLF after bulk string", end));
+            }
+            let result = f(&buf[..len]);
+            self.reader.consume(len + 2);
+            self.offset += len as u64 + 2;
+            self.line_start = true;
+            return result;
+        }
+        scratch.clear();
+        self.read_payload(len, scratch)?;
+        self.read_bulk_end()?;
+        f(scratch)
+    }
+
+    /// Append a bulk string payload of `len` bytes to `buf`, which grows as
+    /// the data arrives instead of by the length the peer has declared
+    fn read_payload(&mut self, len: usize, buf: &mut Vec<u8>) -> Result<()> {
+        let read = (&mut *self.reader).take(len as u64).read_to_end(buf)?;
+        self.offset += read as u64;
+        if read < len {
+            return Err(Error::Eof);
+        }
+        Ok(())
+    }
+
+    fn check_depth(&self) -> Result<()> {
+        if self.path.len() >= self.max_depth {
+            return Err(Error::DepthLimit(self.max_depth));
+        }
+        Ok(())
+    }
+
+    /// Length of a bulk string or an array, `None` for null values
+    fn parse_length(&mut self) -> Result<Option<usize>> {
+        let size = self.parse_int()?;
+        if size >= 0 {
+            Ok(Some(size as usize))
+        } else if self.strict && size != -1 {
+            Err(Error::Strict(Violation::NegativeLength))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn read_prefix(&mut self) -> Result<u8> {
+        if let Some(prefix) = self.peeked.take() {
+            self.offset += 1;
+            return Ok(prefix);
+        }
+        let prefix = match self.reader.fill_buf()?.first() {
+            Some(&prefix) => prefix,
+            None => return Err(Error::Eof),
+        };
+        self.reader.consume(1);
+        self.offset += 1;
+        self.line_start = false;
+        Ok(prefix)
+    }
+
+    /// Make `read_prefix` return `prefix` again
+    fn unread_prefix(&mut self, prefix: u8) {
+        self.peeked = Some(prefix);
+        self.offset -= 1;
+    }
+
+    /// Read the prefix of the next value and parse the rest of it with `parse`.
+    /// Errors get the position of the value attached, unless a nested value
+    /// has done it already.
+    fn parse_located<T, F>(&mut self, parse: F) -> Result<T>
+    where
+        F: FnOnce(&mut Self, u8) -> Result<T>,
+    {
+        let offset = self.offset;
+        let (prefix, result) = match self.read_prefix() {
+            Ok(prefix) => (Some(prefix), parse(self, prefix)),
+            Err(err) => (None, Err(err)),
+        };
+        result.map_err(|error| match error {
+            Error::At(_) => error,
+            error => Error::At(Box::new(Position {
+                offset,
+                path: self.path.clone(),
+                prefix,
+                error,
+            })),
+        })
+    }
+
+    /// Parse a value of the type given by its prefix
+    fn parse_value<V>(&mut self, prefix: u8, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match prefix {
+            _ if self.is_inline(prefix) => {
+                let args = match self.read_inline(prefix)? {
+                    Inline::Command(args) => args,
+                    Inline::Value(prefix) => return self.parse_value(prefix, visitor),
+                };
+                let mut seq: SeqDeserializer<_, Error> = SeqDeserializer::new(
+                    args.into_iter().map(|arg| RESPType::BulkString(Some(arg))),
+                );
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            b'+' => self.with_line(|line| visit_simple_str(line, visitor)),
+            b'-' => visit_error(self.read_line()?, visitor),
+            b':' => visitor.visit_i64(self.parse_int()?),
+            b'#' => visitor.visit_bool(self.parse_bool()?),
+            b'$' => match self.read_bulk_string()? {
+                Some(bytes) => visitor.visit_byte_buf(bytes),
+                None => visitor.visit_none(),
+            },
+            b'*' => self.parse_array(visitor),
+            _ => Err(Error::syntax(EXPECTED_PREFIX, &[prefix])),
+        }
+    }
+
+    /// RESPType::BulkString, prefix is already consumed
+    fn read_bulk_string(&mut self) -> Result<Option<Vec<u8>>> {
+        let size = match self.parse_length()? {
+            Some(size) => size,
+            None => return Ok(None),
+        };
+        let mut buf = Vec::new();
+        self.read_payload(size, &mut buf)?;
+        self.read_bulk_end()?;
+        Ok(Some(buf))
+    }
+
+    /// Consume the CRLF after the payload of a bulk string
+    ///
+    /// Anything else is a syntax error, even if the input ends early.
+    fn read_bulk_end(&mut self) -> Result<()> {
+        let mut end = [0u8; 2];
+        let mut len = 0;
+        while len < end.len() {
+            match self.reader.read(&mut end[len..]) {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        self.offset += len as u64;
+        let end = &end[..len];
+        if end.is_empty() {
+            return Err(Error::Eof);
+        }
+        self.line_start = end.last() == Some(&b'\n');
+        if end != b"\r\n" {
+            return Err(Error::syntax("CRLF after bulk string", end));
+        }
+        Ok(())
+    }
+
+    /// Consume `len` bytes without copying them
+    fn skip_bytes(&mut self, mut len: u64) -> Result<()> {
+        while len > 0 {
+            let available = self.reader.fill_buf()?.len();
+            if available == 0 {
+                return Err(Error::Eof);
+            }
+            let n = available.min(len.try_into().unwrap_or(usize::MAX));
+            self.reader.consume(n);
+            self.offset += n as u64;
+            len -= n as u64;
+        }
+        Ok(())
+    }
+
+    /// Skip the next value without buffering bulk strings or recursing into
+    /// arrays
+    fn skip_value(&mut self) -> Result<()> {
+        let depth = self.path.len();
+        let result = self.skip_value_iterative();
+        self.path.truncate(depth);
+        result
+    }
+
+    fn skip_value_iterative(&mut self) -> Result<()> {
+        // Elements left in the arrays which are being skipped
+        let mut arrays: Vec<usize> = Vec::new();
+        loop {
+            let len = self.parse_located(Self::skip_frame)?;
+            if len > 0 {
+                arrays.push(len);
+                self.path.push(0);
+                continue;
+            }
+            loop {
+                let left = match arrays.last_mut() {
+                    Some(left) => left,
+                    None => return Ok(()),
+                };
+                *left -= 1;
+                if *left > 0 {
+                    if let Some(index) = self.path.last_mut() {
+                        *index += 1;
+                    }
+                    break;
+                }
+                arrays.pop();
+                self.path.pop();
+            }
+        }
+    }
+
+    /// Skip a value except for array elements, returns their number
+    fn skip_frame(&mut self, prefix: u8) -> Result<usize> {
+        match prefix {
+            _ if self.is_inline(prefix) => {
+                if let Inline::Value(prefix) = self.read_inline(prefix)? {
+                    return self.skip_frame(prefix);
+                }
+            }
+            b'+' | b'-' => self.with_line(|_| Ok(()))?,
+            b':' => {
+                self.parse_int()?;
+            }
+            b'#' => {
+                self.parse_bool()?;
+            }
+            b'$' => {
+                if let Some(len) = self.parse_length()? {
+                    self.skip_bytes(len as u64)?;
+                    self.read_bulk_end()?;
+                }
+            }
+            b'*' => return Ok(self.parse_length()?.unwrap_or(0)),
+            _ => return Err(Error::syntax(EXPECTED_PREFIX, &[prefix])),
+        }
+        Ok(0)
+    }
+
+    /// Read a value with its exact type for `RESPType`
+    fn parse_frame(&mut self, prefix: u8) -> Result<Frame> {
+        Ok(match prefix {
+            _ if self.is_inline(prefix) => match self.read_inline(prefix)? {
+                Inline::Command(args) => Frame::Inline(args),
+                Inline::Value(prefix) => return self.parse_frame(prefix),
+            },
+            b'+' => Frame::SimpleString(self.read_line()?),
+            b'-' => Frame::Error(self.read_line()?),
+            b':' => Frame::Integer(self.parse_int()?),
+            // There is no boolean type in RESP2
+            b'#' => Frame::Integer(self.parse_bool()? as i64),
+            b'$' => Frame::BulkString(self.read_bulk_string()?),
+            b'*' => {
+                let len = self.parse_length()?;
+                if len.is_some() {
+                    self.check_depth()?;
+                }
+                Frame::Array(len)
+            }
+            _ => return Err(Error::syntax(EXPECTED_PREFIX, &[prefix])),
+        })
+    }
+
+    /// Whether a value starting with `prefix` is an inline command. As in
+    /// Redis, that is any top-level value which doesn't start with `*`.
+    fn is_inline(&self, prefix: u8) -> bool {
+        self.inline_commands && prefix != b'*' && self.path.is_empty()
+    }
+
+    /// Read the inline command which starts with `prefix`, after any number
+    /// of empty lines. They are skipped in a loop, so a peer sending nothing
+    /// but CRLFs can't exhaust the stack.
+    fn read_inline(&mut self, mut prefix: u8) -> Result<Inline> {
+        loop {
+            if let Some(args) = self.read_inline_command(prefix)? {
+                return Ok(Inline::Command(args));
+            }
+            prefix = self.read_prefix()?;
+            if !self.is_inline(prefix) {
+                return Ok(Inline::Value(prefix));
+            }
+        }
+    }
+
+    /// Arguments of an inline command which starts with `first` byte, `None`
+    /// for an empty line
+    fn read_inline_command(&mut self, first: u8) -> Result<Option<Vec<Vec<u8>>>> {
+        let mut line = vec![first];
+        if first != b'\n' {
+            line.extend(self.read_line()?);
+        }
+        let args = split_args(&line)?;
+        Ok(if args.is_empty() { None } else { Some(args) })
+    }
+
+    /// Number written as an integer, or as text in a simple or bulk string
+    fn deserialize_number<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.parse_located(|de, prefix| match prefix {
+            b'+' if !de.is_inline(prefix) => de.with_line(|line| visit_number(line, visitor)),
+            b'$' if !de.is_inline(prefix) => match de.read_bulk_string()? {
+                Some(bytes) => visit_number(&bytes, visitor),
+                None => visitor.visit_none(),
+            },
+            prefix => de.parse_value(prefix, visitor),
+        })
+    }
+
+    /// RESPType::Array, prefix is already consumed
+    fn parse_array<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.parse_length()? {
+            Some(size) => {
+                self.check_depth()?;
+                visitor.visit_seq(RESPArray::new(self, size))
+            }
+            None => visitor.visit_unit(),
         }
     }
 }
 
-impl<'de, 'a, R: BufRead> de::Deserializer<'de> for &'a mut Deserializer<'de, R> {
+impl<'de, R: BufRead> de::Deserializer<'de> for &mut Deserializer<'de, R> {
     type Error = Error;
 
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        let mut buf = [0u8; 1];
-        self.reader.read_exact(&mut buf)?;
-        match buf[0] {
-            b'+' => self.deserialize_str(visitor),
-            b'-' => self.deserialize_string(visitor),
-            b':' => self.deserialize_i64(visitor),
-            b'$' => self.deserialize_byte_buf(visitor),
-            b'*' => self.deserialize_seq(visitor),
-            _ => Err(Error::Syntax),
-        }
+        self.parse_located(|de, prefix| de.parse_value(prefix, visitor))
     }
 
-    fn deserialize_bool<V>(self, _visitor: V) -> Result<V::Value>
+    // Redis answers yes/no questions with `:0` and `:1`, RESP3 has `#f` and
+    // `#t` for them.
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        self.parse_located(|de, prefix| match prefix {
+            b':' if !de.is_inline(prefix) => visitor.visit_bool(integer_to_bool(de.parse_int()?)?),
+            prefix => de.parse_value(prefix, visitor),
+        })
     }
 
-    // The `parse_signed` function is generic over the integer type `T` so here
-    // it is invoked with `T=i8`. The next 8 methods are similar.
-    fn deserialize_i8<V>(self, _visitor: V) -> Result<V::Value>
+    // Integers come as `:` integers, but many replies carry numbers in bulk
+    // strings, like scores of sorted sets. Their text is parsed, and the
+    // visitor checks that the number fits its type.
+    fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        self.deserialize_number(visitor)
     }
 
-    fn deserialize_i16<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        self.deserialize_number(visitor)
     }
 
-    fn deserialize_i32<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        self.deserialize_number(visitor)
     }
 
     fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        let mut buf = String::new();
-        self.reader.read_line(&mut buf)?;
-        match buf.trim_end().parse::<i64>() {
-            Ok(i) => visitor.visit_i64(i),
-            Err(_) => Err(Error::Syntax),
-        }
+        self.deserialize_number(visitor)
     }
 
-    fn deserialize_u8<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        self.deserialize_number(visitor)
     }
 
-    fn deserialize_u16<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        self.deserialize_number(visitor)
     }
 
-    fn deserialize_u32<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        self.deserialize_number(visitor)
     }
 
-    fn deserialize_u64<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        self.deserialize_number(visitor)
     }
 
-    // Float parsing is stupidly hard.
-    fn deserialize_f32<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        self.deserialize_number(visitor)
     }
 
-    // Float parsing is stupidly hard.
-    fn deserialize_f64<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        self.deserialize_number(visitor)
     }
 
     // The `Serializer` implementation on the previous page serialized chars as
     // single-character strings so handle that representation here.
-    fn deserialize_char<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        // Parse a string, check that it is one character, call `visit_char`.
-        unimplemented!()
+        self.parse_located(|de, prefix| match prefix {
+            b'+' if !de.is_inline(prefix) => de.with_line(|line| visit_char(line, visitor)),
+            b'$' if !de.is_inline(prefix) => match de.read_bulk_string()? {
+                Some(bytes) => visit_char(&bytes, visitor),
+                None => visitor.visit_none(),
+            },
+            prefix => de.parse_value(prefix, visitor),
+        })
     }
 
     // Refer to the "Understanding deserializer lifetimes" page for information
@@ -167,100 +833,113 @@ impl<'de, 'a, R: BufRead> de::Deserializer<'de> for &'a mut Deserializer<'de, R>
     where
         V: Visitor<'de>,
     {
-        let mut buf = String::new();
-        self.reader.read_line(&mut buf)?;
-        visitor.visit_str(buf.trim_end())
+        self.deserialize_any(visitor)
     }
 
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        let mut buf = String::new();
-        self.reader.read_line(&mut buf)?;
-        visitor.visit_string(buf.trim_end().to_string())
+        self.deserialize_any(visitor)
     }
 
-    // The `Serializer` implementation on the previous page serialized byte
-    // arrays as JSON arrays of bytes. Handle that representation here.
-    fn deserialize_bytes<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        self.deserialize_any(visitor)
     }
 
     fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        let size = self.parse_isize()?;
-        if size < 0 {
-            return visitor.visit_none();
-        }
-        let mut buf = vec![0u8; (size + 2) as usize];
-        self.reader.read_exact(&mut buf)?;
-        if buf.split_off(size as usize) != b"\r\n" {
-            return Err(Error::Syntax);
-        }
-        visitor.visit_byte_buf(buf)
+        self.deserialize_any(visitor)
     }
 
-    // An absent optional is represented as the JSON `null` and a present
-    // optional is represented as just the contained value.
-    //
-    // As commented in `Serializer` implementation, this is a lossy
-    // representation. For example the values `Some(())` and `None` both
-    // serialize as just `null`. Unfortunately this is typically what people
-    // expect when working with JSON. Other formats are encouraged to behave
-    // more intelligently if possible.
-    fn deserialize_option<V>(self, _visitor: V) -> Result<V::Value>
+    // An absent optional is a null bulk string or a null array, which have a
+    // negative length. Anything else is the contained value, so its prefix is
+    // put back for the visitor to read it.
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        let null = self.parse_located(|de, prefix| {
+            let null = matches!(prefix, b'$' | b'*')
+                && !de.is_inline(prefix)
+                && de.reader.fill_buf()?.first() == Some(&b'-');
+            if null {
+                de.parse_length()?;
+            } else {
+                de.unread_prefix(prefix);
+            }
+            Ok(null)
+        })?;
+        visit_option(Some(self).filter(|_| !null), visitor)
     }
 
     // In Serde, unit means an anonymous value containing no data.
-    fn deserialize_unit<V>(self, _visitor: V) -> Result<V::Value>
+    //
+    // Here it is the `+OK` status, other simple strings are rejected. Null
+    // bulk strings and null arrays are unit as well, so every `UnitRepr`
+    // reads back.
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        self.parse_located(|de, prefix| match prefix {
+            b'+' if !de.is_inline(prefix) => {
+                de.with_line(check_ok)?;
+                visitor.visit_unit()
+            }
+            b'$' if !de.is_inline(prefix) => match de.read_bulk_string()? {
+                Some(bytes) => visitor.visit_byte_buf(bytes),
+                None => visitor.visit_unit(),
+            },
+            prefix => de.parse_value(prefix, visitor),
+        })
     }
 
     // Unit struct means a named value containing no data.
-    fn deserialize_unit_struct<V>(self, _name: &'static str, _visitor: V) -> Result<V::Value>
+    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        self.deserialize_unit(visitor)
     }
 
     // As is done here, serializers are encouraged to treat newtype structs as
     // insignificant wrappers around the data they contain. That means not
     // parsing anything other than the contained value.
-    fn deserialize_newtype_struct<V>(self, _name: &'static str, _visitor: V) -> Result<V::Value>
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        match name {
+            RESP_TYPE_TOKEN => self.parse_located(|de, prefix| {
+                let frame = de.parse_frame(prefix)?;
+                visitor.visit_enum(RESPFrame { de, frame })
+            }),
+            _ => visitor.visit_newtype_struct(self),
+        }
     }
 
     // Deserialization of compound types like sequences and maps happens by
     // passing the visitor an "Access" object that gives it the ability to
     // iterate through the data contained in the sequence.
-    fn deserialize_seq<V>(mut self, visitor: V) -> Result<V::Value>
+    //
+    // Bulk strings are also accepted here so that byte buffers like `Vec<u8>`
+    // can be read back from what `Serializer` writes for them.
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        let size = self.parse_isize()?;
-        if size < 0 {
-            return visitor.visit_unit();
-        }
-        visitor.visit_seq(RESPArray {
-            de: &mut self,
-            remaining: size as usize,
+        self.parse_located(|de, prefix| match prefix {
+            b'$' if !de.is_inline(prefix) => match de.read_bulk_string()? {
+                Some(bytes) => visit_bytes_as_seq(bytes, visitor),
+                None => visitor.visit_none(),
+            },
+            prefix => de.parse_value(prefix, visitor),
         })
     }
 
@@ -270,34 +949,77 @@ impl<'de, 'a, R: BufRead> de::Deserializer<'de> for &'a mut Deserializer<'de, R>
     // As indicated by the length parameter, the `Deserialize` implementation
     // for a tuple in the Serde data model is required to know the length of the
     // tuple before even looking at the input data.
-    fn deserialize_tuple<V>(self, _len: usize, _visitor: V) -> Result<V::Value>
+    //
+    // Arrays and bulk strings of other length fail with
+    // `Error::LengthMismatch`, arrays are skipped before that.
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        self.parse_located(|de, prefix| match prefix {
+            b'*' => match de.parse_length()? {
+                Some(found) => {
+                    if let Err(err) = check_tuple_len(len, found, false) {
+                        for index in 0..found {
+                            de.path.push(index);
+                            let skipped = de.skip_value();
+                            de.path.pop();
+                            skipped?;
+                        }
+                        return Err(err);
+                    }
+                    de.check_depth()?;
+                    visitor.visit_seq(RESPArray::new(de, len))
+                }
+                None => visitor.visit_unit(),
+            },
+            b'$' if !de.is_inline(prefix) => match de.read_bulk_string()? {
+                Some(bytes) => {
+                    check_tuple_len(len, bytes.len(), true)?;
+                    visit_bytes_as_seq(bytes, visitor)
+                }
+                None => visitor.visit_none(),
+            },
+            prefix => de.parse_value(prefix, visitor),
+        })
     }
 
     // Tuple structs look just like sequences in JSON.
     fn deserialize_tuple_struct<V>(
         self,
         _name: &'static str,
-        _len: usize,
-        _visitor: V,
+        len: usize,
+        visitor: V,
     ) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        self.deserialize_tuple(len, visitor)
     }
 
     // Much like `deserialize_seq` but calls the visitors `visit_map` method
     // with a `MapAccess` implementation, rather than the visitor's `visit_seq`
     // method with a `SeqAccess` implementation.
-    fn deserialize_map<V>(self, _visitor: V) -> Result<V::Value>
+    //
+    // Maps are flat arrays of keys followed by their values, as in replies
+    // to `HGETALL`, `CONFIG GET` or `XINFO`.
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        self.parse_located(|de, prefix| {
+            if prefix != b'*' {
+                return Err(Error::syntax("array", &[prefix]));
+            }
+            match de.parse_length()? {
+                Some(len) => {
+                    check_map_len(len)?;
+                    de.check_depth()?;
+                    visitor.visit_map(RESPArray::new(de, len))
+                }
+                None => visitor.visit_unit(),
+            }
+        })
     }
 
     // Structs look just like maps in JSON.
@@ -306,16 +1028,18 @@ impl<'de, 'a, R: BufRead> de::Deserializer<'de> for &'a mut Deserializer<'de, R>
     // that the `Deserialize` implementation is required to know what the fields
     // are before even looking at the input data. Any key-value pairing in which
     // the fields cannot be known ahead of time is probably a map.
+    //
+    // Unknown fields are skipped by `deserialize_ignored_any`.
     fn deserialize_struct<V>(
         self,
         _name: &'static str,
         _fields: &'static [&'static str],
-        _visitor: V,
+        visitor: V,
     ) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        self.deserialize_map(visitor)
     }
 
     fn deserialize_enum<V>(
@@ -327,18 +1051,20 @@ impl<'de, 'a, R: BufRead> de::Deserializer<'de> for &'a mut Deserializer<'de, R>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        // Skipped, so that reading can go on with the next value
+        self.skip_value()?;
+        Err(Error::unsupported("enum"))
     }
 
     // An identifier in Serde is the type that identifies a field of a struct or
     // the variant of an enum. In JSON, struct fields and enum variants are
     // represented as strings. In other formats they may be represented as
     // numeric indices.
-    fn deserialize_identifier<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        self.deserialize_any(visitor)
     }
 
     // Like `deserialize_any` but indicates to the `Deserializer` that it makes
@@ -352,137 +1078,752 @@ impl<'de, 'a, R: BufRead> de::Deserializer<'de> for &'a mut Deserializer<'de, R>
     // Some formats are not able to implement this at all. Formats that can
     // implement `deserialize_any` and `deserialize_ignored_any` are known as
     // self-describing.
-    fn deserialize_ignored_any<V>(self, _visitor: V) -> Result<V::Value>
+    //
+    // Here values are skipped without being stored, bulk strings are
+    // consumed straight from the reader's buffer.
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        self.skip_value()?;
+        visitor.visit_unit()
     }
 }
 
-struct RESPArray<'a, 'de, R: BufRead> {
-    de: &'a mut Deserializer<'de, R>,
-    remaining: usize,
-}
+pub(crate) const PREFIXES: &[u8] = b"+-:$*#";
+/// Longest line of an integer, a boolean or a length, with its LF. Redis
+/// limits such lines to 64 KiB as well.
+pub(crate) const MAX_HEADER_LEN: usize = 64 * 1024;
+pub(crate) const EXPECTED_PREFIX: &str = "one of `+-:$*#`";
 
-impl<'a, 'de, R: BufRead> de::SeqAccess<'de> for RESPArray<'a, 'de, R> {
-    type Error = Error;
+/// Strip the line terminator, `line` ends with LF unless the input has ended.
+///
+/// Outside of strict mode a lone LF is accepted as well. Other whitespace is
+/// part of the line, so strings are passed on without losing bytes.
+pub(crate) fn trim_line(line: &[u8], strict: bool) -> Result<&[u8]> {
+    if !strict {
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        return Ok(line.strip_suffix(b"\r").unwrap_or(line));
+    }
+    let line = line.strip_suffix(b"\n").ok_or(Error::Eof)?;
+    let line = line
+        .strip_suffix(b"\r")
+        .ok_or(Error::Strict(Violation::MissingCarriageReturn))?;
+    if memchr(b'\r', line).is_some() {
+        return Err(Error::Strict(Violation::StrayCarriageReturn));
+    }
+    Ok(line)
+}
 
-    fn next_element_seed<T>(
-        &mut self,
-        seed: T,
-    ) -> std::result::Result<Option<<T as DeserializeSeed<'de>>::Value>, Self::Error>
-    where
-        T: DeserializeSeed<'de>,
-    {
-        if self.remaining == 0 {
-            return Ok(None);
+/// Parse a decimal integer with an optional sign and trailing whitespace,
+/// `None` if it is malformed or out of range
+pub(crate) fn parse_integer(line: &[u8]) -> Option<i64> {
+    let len = line
+        .iter()
+        .rposition(|b| !b.is_ascii_whitespace())
+        .map_or(0, |i| i + 1);
+    let line = &line[..len];
+    let (negative, digits) = match line.split_first()? {
+        (b'-', digits) => (true, digits),
+        (b'+', digits) => (false, digits),
+        _ => (false, line),
+    };
+    if digits.is_empty() {
+        return None;
+    }
+    // Accumulate negative values so that `i64::MIN` fits
+    let mut value: i64 = 0;
+    for &byte in digits {
+        if !byte.is_ascii_digit() {
+            return None;
         }
-        self.remaining -= 1; // remove read element from the remaining count
-        seed.deserialize(&mut *self.de).map(Some)
+        value = value.checked_mul(10)?.checked_sub((byte - b'0') as i64)?;
     }
+    if negative {
+        Some(value)
+    } else {
+        value.checked_neg()
+    }
+}
 
-    fn size_hint(&self) -> Option<usize> {
-        Some(self.remaining)
+/// Check that integer or length is written in the canonical form
+fn check_integer(line: &[u8]) -> Result<()> {
+    let digits = line.strip_prefix(b"-").unwrap_or(line);
+    match (digits.first(), line.last()) {
+        (Some(b'+'), _) => Err(Error::Strict(Violation::PlusSign)),
+        (Some(first), _) if first.is_ascii_whitespace() => {
+            Err(Error::Strict(Violation::Whitespace))
+        }
+        (_, Some(last)) if last.is_ascii_whitespace() => Err(Error::Strict(Violation::Whitespace)),
+        (Some(b'0'), _) if digits.len() > 1 || digits.len() < line.len() => {
+            Err(Error::Strict(Violation::LeadingZero))
+        }
+        _ => Ok(()),
     }
 }
 
-struct RESPTypeVisitor;
+/// Unit is the `+OK` status, other simple strings are rejected
+pub(crate) fn check_ok(line: &[u8]) -> Result<()> {
+    match line {
+        b"OK" => Ok(()),
+        _ => Err(Error::syntax("OK", line)),
+    }
+}
 
-impl<'de> de::Visitor<'de> for RESPTypeVisitor {
-    type Value = RESPType;
+/// Arrays and bulk strings read as a tuple must have its length
+pub(crate) fn check_tuple_len(expected: usize, found: usize, bulk_string: bool) -> Result<()> {
+    if found != expected {
+        return Err(Error::LengthMismatch {
+            expected,
+            found,
+            bulk_string,
+        });
+    }
+    Ok(())
+}
 
-    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("A RESP value")
+/// Keys and values of a map alternate in an array
+pub(crate) fn check_map_len(len: usize) -> Result<()> {
+    if len % 2 != 0 {
+        return Err(de::Error::invalid_length(len, &"even number of elements"));
     }
+    Ok(())
+}
 
-    fn visit_i64<E>(self, v: i64) -> std::result::Result<Self::Value, E>
-    where
-        E: de::Error,
-    {
-        Ok(RESPType::Integer(v))
+/// Nulls are `None`, anything else is the contained value
+pub(crate) fn visit_option<'de, V, D>(value: Option<D>, visitor: V) -> Result<V::Value>
+where
+    V: Visitor<'de>,
+    D: de::Deserializer<'de, Error = Error>,
+{
+    match value {
+        Some(value) => visitor.visit_some(value),
+        None => visitor.visit_none(),
     }
+}
 
-    fn visit_str<E>(self, v: &str) -> std::result::Result<Self::Value, E>
-    where
-        E: de::Error,
-    {
-        Ok(RESPType::SimpleString(v.to_string()))
+/// Simple strings are handed over as `str` when they are valid UTF-8 and as
+/// bytes otherwise
+pub(crate) fn visit_simple_str<'de, V>(bytes: &[u8], visitor: V) -> Result<V::Value>
+where
+    V: Visitor<'de>,
+{
+    match std::str::from_utf8(bytes) {
+        Ok(string) => visitor.visit_str(string),
+        Err(_) => visitor.visit_bytes(bytes),
     }
+}
 
-    fn visit_string<E>(self, v: String) -> std::result::Result<Self::Value, E>
-    where
-        E: de::Error,
-    {
-        Ok(RESPType::Error(v))
+/// `visit_simple_str` for a simple string which is already owned
+pub(crate) fn visit_simple_string<'de, V>(bytes: Vec<u8>, visitor: V) -> Result<V::Value>
+where
+    V: Visitor<'de>,
+{
+    match String::from_utf8(bytes) {
+        Ok(string) => visitor.visit_string(string),
+        Err(err) => visitor.visit_byte_buf(err.into_bytes()),
     }
+}
 
-    fn visit_byte_buf<E>(self, v: Vec<u8>) -> std::result::Result<Self::Value, E>
-    where
-        E: de::Error,
-    {
-        Ok(RESPType::BulkString(Some(v)))
+/// `0` is false and `1` is true, other integers are rejected
+pub(crate) fn integer_to_bool(int: i64) -> Result<bool> {
+    match int {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(de::Error::invalid_value(Unexpected::Signed(int), &"0 or 1")),
     }
+}
 
-    fn visit_none<E>(self) -> std::result::Result<Self::Value, E>
-    where
-        E: de::Error,
-    {
-        Ok(RESPType::BulkString(None))
+/// RESP3 boolean, `t` or `f`
+pub(crate) fn parse_boolean(line: &[u8]) -> Result<bool> {
+    match line {
+        b"t" => Ok(true),
+        b"f" => Ok(false),
+        _ => Err(Error::syntax("`t` or `f`", line)),
     }
+}
 
-    fn visit_unit<E>(self) -> std::result::Result<Self::Value, E>
-    where
-        E: de::Error,
-    {
-        Ok(RESPType::Array(None))
+/// Strings holding a decimal number are handed over as `i64`, as `u64` if
+/// it's too big, or as `f64`. Redis writes infinite scores as `inf`, which
+/// is accepted too.
+pub(crate) fn visit_number<'de, V>(bytes: &[u8], visitor: V) -> Result<V::Value>
+where
+    V: Visitor<'de>,
+{
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(_) => return Err(de::Error::invalid_type(Unexpected::Bytes(bytes), &visitor)),
+    };
+    if let Ok(int) = text.parse() {
+        visitor.visit_i64(int)
+    } else if let Ok(int) = text.parse() {
+        visitor.visit_u64(int)
+    } else if let Ok(float) = text.parse() {
+        visitor.visit_f64(float)
+    } else {
+        Err(de::Error::invalid_type(Unexpected::Str(text), &visitor))
     }
+}
 
-    fn visit_seq<A>(
-        self,
-        mut seq: A,
-    ) -> std::result::Result<Self::Value, <A as SeqAccess<'de>>::Error>
-    where
-        A: SeqAccess<'de>,
-    {
-        let mut array = Vec::with_capacity(seq.size_hint().unwrap_or_default());
-        while let Some(element) = seq.next_element()? {
-            array.push(element)
+/// Strings holding exactly one character are handed over as `char`
+pub(crate) fn visit_char<'de, V>(bytes: &[u8], visitor: V) -> Result<V::Value>
+where
+    V: Visitor<'de>,
+{
+    let string = match std::str::from_utf8(bytes) {
+        Ok(string) => string,
+        Err(_) => {
+            return Err(de::Error::invalid_value(
+                Unexpected::Bytes(bytes),
+                &"a character",
+            ))
         }
-        Ok(RESPType::Array(Some(array)))
+    };
+    let mut chars = string.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => visitor.visit_char(c),
+        _ => Err(de::Error::invalid_length(
+            string.chars().count(),
+            &"a single character",
+        )),
     }
 }
 
-impl<'de> de::Deserialize<'de> for RESPType {
-    fn deserialize<D>(
-        deserializer: D,
-    ) -> std::result::Result<Self, <D as de::Deserializer<'de>>::Error>
-    where
-        D: de::Deserializer<'de>,
-    {
-        deserializer.deserialize_any(RESPTypeVisitor)
+/// Errors are handed over as `String` when they are valid UTF-8 and as bytes
+/// otherwise
+pub(crate) fn visit_error<'de, V>(bytes: Vec<u8>, visitor: V) -> Result<V::Value>
+where
+    V: Visitor<'de>,
+{
+    match String::from_utf8(bytes) {
+        Ok(string) => visitor.visit_string(string),
+        Err(err) => visitor.visit_byte_buf(err.into_bytes()),
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Hand bytes of a bulk string to a visitor that expects a sequence
+pub(crate) fn visit_bytes_as_seq<'de, V>(bytes: Vec<u8>, visitor: V) -> Result<V::Value>
+where
+    V: Visitor<'de>,
+{
+    let mut seq: SeqDeserializer<_, Error> = SeqDeserializer::new(bytes.into_iter());
+    let value = visitor.visit_seq(&mut seq)?;
+    seq.end()?;
+    Ok(value)
+}
 
-    #[test]
-    fn de_simple_string() {
-        let s = String::from("+Hello\r\n");
-        let result: RESPType = from_string(s).unwrap();
-        assert_eq!(result, RESPType::SimpleString("Hello".to_string()));
+/// Payload of a bulk string, returned by `Deserializer::read_bulk_stream`
+pub struct BulkReader<'a, 'de, R: BufRead> {
+    de: &'a mut Deserializer<'de, R>,
+    len: u64,
+    remaining: u64,
+    done: bool,
+}
+
+impl<'a, 'de, R: BufRead> BulkReader<'a, 'de, R> {
+    /// Declared length of the bulk string
+    pub fn len(&self) -> u64 {
+        self.len
     }
 
-    #[test]
-    fn de_error() {
-        let s = String::from("-UNEXPECTED\r\n");
-        let result: RESPType = from_string(s).unwrap();
-        assert_eq!(result, RESPType::Error("UNEXPECTED".to_string()));
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
     }
 
-    #[test]
+    /// Consume the CRLF after the payload
+    fn finish(&mut self) -> Result<()> {
+        if self.done {
+            return Ok(());
+        }
+        self.done = true;
+        self.de.read_bulk_end()
+    }
+}
+
+impl<'a, 'de, R: BufRead> io::Read for BulkReader<'a, 'de, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.remaining == 0 {
+            self.finish().map_err(into_io_error)?;
+            return Ok(0);
+        }
+        let max = buf
+            .len()
+            .min(self.remaining.try_into().unwrap_or(usize::MAX));
+        let n = self.de.reader.read(&mut buf[..max])?;
+        if n == 0 && max > 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.remaining -= n as u64;
+        self.de.offset += n as u64;
+        Ok(n)
+    }
+}
+
+impl<'a, 'de, R: BufRead> Drop for BulkReader<'a, 'de, R> {
+    fn drop(&mut self) {
+        // Errors show up on the next read anyway
+        if self.de.skip_bytes(self.remaining).is_ok() {
+            let _ = self.finish();
+        }
+    }
+}
+
+/// Elements of an array, returned by `Deserializer::read_array_stream`.
+/// Iterating over it yields elements as `RESPType`.
+pub struct ArrayReader<'a, 'de, R: BufRead> {
+    de: &'a mut Deserializer<'de, R>,
+    len: usize,
+    index: usize,
+    /// Position in the stream is unknown after an error
+    failed: bool,
+}
+
+impl<'a, 'de, R: BufRead> ArrayReader<'a, 'de, R> {
+    /// Declared length of the array
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Read the next element, `None` after the last one or after an error
+    pub fn next_element<T: de::Deserialize<'de>>(&mut self) -> Result<Option<T>> {
+        self.next_with(|de| T::deserialize(de))
+    }
+
+    /// Skip the elements which are left
+    pub fn skip_remaining(&mut self) -> Result<()> {
+        while self.next_with(Deserializer::skip_value)?.is_some() {}
+        Ok(())
+    }
+
+    fn next_with<T, F>(&mut self, read: F) -> Result<Option<T>>
+    where
+        F: FnOnce(&mut Deserializer<'de, R>) -> Result<T>,
+    {
+        if self.failed || self.index == self.len {
+            return Ok(None);
+        }
+        self.de.path.push(self.index);
+        let result = read(self.de);
+        self.de.path.pop();
+        self.index += 1;
+        self.failed = result.is_err();
+        result.map(Some)
+    }
+}
+
+impl<'a, 'de, R: BufRead> Iterator for ArrayReader<'a, 'de, R> {
+    type Item = Result<RESPType>;
+
+    fn next(&mut self) -> Option<Result<RESPType>> {
+        self.next_element().transpose()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = if self.failed {
+            0
+        } else {
+            self.len - self.index
+        };
+        (0, Some(remaining))
+    }
+}
+
+impl<'a, 'de, R: BufRead> Drop for ArrayReader<'a, 'de, R> {
+    fn drop(&mut self) {
+        // Errors show up on the next read anyway
+        let _ = self.skip_remaining();
+    }
+}
+
+fn into_io_error(error: Error) -> io::Error {
+    match error {
+        Error::Io(error) => {
+            Arc::try_unwrap(error).unwrap_or_else(|error| io::Error::new(error.kind(), error))
+        }
+        Error::Eof => io::ErrorKind::UnexpectedEof.into(),
+        error => io::Error::new(io::ErrorKind::InvalidData, error),
+    }
+}
+
+struct RESPArray<'a, 'de, R: BufRead> {
+    de: &'a mut Deserializer<'de, R>,
+    index: usize,
+    remaining: usize,
+}
+
+impl<'a, 'de, R: BufRead> RESPArray<'a, 'de, R> {
+    fn new(de: &'a mut Deserializer<'de, R>, size: usize) -> Self {
+        RESPArray {
+            de,
+            index: 0,
+            remaining: size,
+        }
+    }
+}
+
+impl<'a, 'de, R: BufRead> de::SeqAccess<'de> for RESPArray<'a, 'de, R> {
+    type Error = Error;
+
+    fn next_element_seed<T>(
+        &mut self,
+        seed: T,
+    ) -> std::result::Result<Option<<T as DeserializeSeed<'de>>::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1; // remove read element from the remaining count
+        self.de.path.push(self.index);
+        self.index += 1;
+        let element = seed.deserialize(&mut *self.de);
+        self.de.path.pop();
+        element.map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+/// Keys and values of a map alternate in the array
+impl<'a, 'de, R: BufRead> de::MapAccess<'de> for RESPArray<'a, 'de, R> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        self.next_element_seed(seed)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        match self.next_element_seed(seed)? {
+            Some(value) => Ok(value),
+            None => Err(de::Error::custom("value is missing")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining / 2)
+    }
+}
+
+/// Header of a value read for `RESPType`, with the payload for everything
+/// except array elements
+enum Frame {
+    SimpleString(Vec<u8>),
+    Error(Vec<u8>),
+    Integer(i64),
+    BulkString(Option<Vec<u8>>),
+    Array(Option<usize>),
+    Inline(Vec<Vec<u8>>),
+}
+
+impl Frame {
+    fn index(&self) -> u32 {
+        match self {
+            Frame::SimpleString(_) => SIMPLE_STRING,
+            Frame::Error(_) => ERROR,
+            Frame::Integer(_) => INTEGER,
+            Frame::BulkString(Some(_)) => BULK_STRING,
+            Frame::BulkString(None) => NULL_BULK_STRING,
+            Frame::Array(Some(_)) | Frame::Inline(_) => ARRAY,
+            Frame::Array(None) => NULL_ARRAY,
+        }
+    }
+}
+
+/// Top-level value read in inline commands mode
+enum Inline {
+    /// Arguments of an inline command
+    Command(Vec<Vec<u8>>),
+    /// Prefix of a value which isn't an inline command, found after empty
+    /// lines
+    Value(u8),
+}
+
+struct RESPFrame<'a, 'de, R: BufRead> {
+    de: &'a mut Deserializer<'de, R>,
+    frame: Frame,
+}
+
+impl<'a, 'de, R: BufRead> EnumAccess<'de> for RESPFrame<'a, 'de, R> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let index: U32Deserializer<Error> = self.frame.index().into_deserializer();
+        let variant = seed.deserialize(index)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'a, 'de, R: BufRead> VariantAccess<'de> for RESPFrame<'a, 'de, R> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.frame {
+            Frame::SimpleString(bytes) | Frame::Error(bytes) | Frame::BulkString(Some(bytes)) => {
+                seed.deserialize(ByteBufDeserializer(bytes))
+            }
+            Frame::Integer(int) => seed.deserialize(int.into_deserializer()),
+            Frame::Array(Some(size)) => {
+                seed.deserialize(SeqAccessDeserializer::new(RESPArray::new(self.de, size)))
+            }
+            Frame::Inline(args) => seed.deserialize(SeqDeserializer::new(
+                args.into_iter().map(|arg| RESPType::BulkString(Some(arg))),
+            )),
+            Frame::BulkString(None) | Frame::Array(None) => Err(de::Error::invalid_type(
+                Unexpected::Unit,
+                &"newtype variant",
+            )),
+        }
+    }
+
+    fn tuple_variant<V>(self, _len: usize, _visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(de::Error::invalid_type(
+            Unexpected::NewtypeVariant,
+            &"tuple variant",
+        ))
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], _visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(de::Error::invalid_type(
+            Unexpected::NewtypeVariant,
+            &"struct variant",
+        ))
+    }
+}
+
+/// Hands owned bytes over with `visit_byte_buf`
+pub(crate) struct ByteBufDeserializer(pub(crate) Vec<u8>);
+
+impl<'de> de::Deserializer<'de> for ByteBufDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_byte_buf(self.0)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+/// Accepts any string or bytes as owned bytes
+struct ByteBuf(Vec<u8>);
+
+impl<'de> de::Deserialize<'de> for ByteBuf {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct ByteBufVisitor;
+
+        impl<'de> Visitor<'de> for ByteBufVisitor {
+            type Value = ByteBuf;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("bytes")
+            }
+
+            fn visit_str<E>(self, v: &str) -> std::result::Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(ByteBuf(v.as_bytes().to_vec()))
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> std::result::Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(ByteBuf(v.to_vec()))
+            }
+
+            fn visit_byte_buf<E>(self, v: Vec<u8>) -> std::result::Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(ByteBuf(v))
+            }
+        }
+
+        deserializer.deserialize_byte_buf(ByteBufVisitor)
+    }
+}
+
+struct RESPTypeVisitor;
+
+impl<'de> de::Visitor<'de> for RESPTypeVisitor {
+    type Value = RESPType;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("A RESP value")
+    }
+
+    fn visit_i64<E>(self, v: i64) -> std::result::Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(RESPType::Integer(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> std::result::Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(RESPType::SimpleString(v.as_bytes().to_vec()))
+    }
+
+    fn visit_string<E>(self, v: String) -> std::result::Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(RESPType::Error(v.into_bytes()))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> std::result::Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(RESPType::SimpleString(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> std::result::Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(RESPType::BulkString(Some(v)))
+    }
+
+    fn visit_none<E>(self) -> std::result::Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(RESPType::BulkString(None))
+    }
+
+    fn visit_unit<E>(self) -> std::result::Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(RESPType::Array(None))
+    }
+
+    fn visit_seq<A>(
+        self,
+        mut seq: A,
+    ) -> std::result::Result<Self::Value, <A as SeqAccess<'de>>::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut array = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+        while let Some(element) = seq.next_element()? {
+            array.push(element)
+        }
+        Ok(RESPType::Array(Some(array)))
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> std::result::Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+
+    /// Exact type of the value, given by deserializers of this crate
+    fn visit_enum<A>(self, data: A) -> std::result::Result<Self::Value, A::Error>
+    where
+        A: EnumAccess<'de>,
+    {
+        let (index, variant): (u32, _) = data.variant()?;
+        Ok(match index {
+            SIMPLE_STRING => RESPType::SimpleString(variant.newtype_variant::<ByteBuf>()?.0),
+            ERROR => RESPType::Error(variant.newtype_variant::<ByteBuf>()?.0),
+            INTEGER => RESPType::Integer(variant.newtype_variant()?),
+            BULK_STRING => RESPType::BulkString(Some(variant.newtype_variant::<ByteBuf>()?.0)),
+            NULL_BULK_STRING => {
+                variant.unit_variant()?;
+                RESPType::BulkString(None)
+            }
+            ARRAY => RESPType::Array(Some(variant.newtype_variant()?)),
+            NULL_ARRAY => {
+                variant.unit_variant()?;
+                RESPType::Array(None)
+            }
+            _ => {
+                return Err(de::Error::invalid_value(
+                    Unexpected::Unsigned(index as u64),
+                    &self,
+                ))
+            }
+        })
+    }
+}
+
+impl<'de> de::Deserialize<'de> for RESPType {
+    fn deserialize<D>(
+        deserializer: D,
+    ) -> std::result::Result<Self, <D as de::Deserializer<'de>>::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(RESP_TYPE_TOKEN, RESPTypeVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Pairs;
+    use serde::Deserialize;
+
+    #[test]
+    fn de_simple_string() {
+        let s = String::from("+Hello\r\n");
+        let result: RESPType = from_string(s).unwrap();
+        assert_eq!(result, RESPType::SimpleString("Hello".into()));
+    }
+
+    #[test]
+    fn de_error() {
+        let s = String::from("-UNEXPECTED\r\n");
+        let result: RESPType = from_string(s).unwrap();
+        assert_eq!(result, RESPType::Error("UNEXPECTED".into()));
+    }
+
+    #[test]
     fn de_integer() {
         let s = String::from(":23\r\n");
         let result: RESPType = from_string(s).unwrap();
@@ -528,9 +1869,683 @@ mod tests {
             result,
             RESPType::Array(Some(vec![
                 RESPType::Integer(23),
-                RESPType::SimpleString("Hello".to_owned()),
+                RESPType::SimpleString("Hello".into()),
                 RESPType::BulkString(Some(String::from("there").into_bytes()))
             ]))
         );
     }
+
+    #[test]
+    fn de_bulk_string_as_bytes() {
+        let s = String::from("*2\r\n$5\r\nHello\r\n*0\r\n");
+        let result: Vec<Vec<u8>> = from_string(s).unwrap();
+        assert_eq!(result, vec![b"Hello".to_vec(), vec![]]);
+    }
+
+    #[test]
+    fn de_array_as_bytes() {
+        let bytes: Vec<u8> = from_string("*2\r\n:1\r\n:2\r\n".to_string()).unwrap();
+        assert_eq!(bytes, [1, 2]);
+        let err = from_string::<Vec<u8>>("*2\r\n:1\r\n:256\r\n".to_string()).unwrap_err();
+        assert_eq!(err.position().unwrap().path, [1]);
+    }
+
+    #[test]
+    fn de_bulk_string_as_string() {
+        let s = String::from("$5\r\nHello\r\n");
+        let result: String = from_string(s).unwrap();
+        assert_eq!(result, "Hello");
+    }
+
+    #[test]
+    fn de_binary_simple_string_and_error() {
+        let mut reader = &b"*2\r\n+\xe9t\xe9\r\n-ERR \xff\r\n"[..];
+        let result: RESPType = from_buf_reader(&mut reader).unwrap();
+        assert_eq!(
+            result,
+            RESPType::Array(Some(vec![
+                RESPType::SimpleString(b"\xe9t\xe9".to_vec()),
+                RESPType::Error(b"ERR \xff".to_vec()),
+            ]))
+        );
+    }
+
+    fn from_strict<T: DeserializeOwned>(s: &str) -> Result<T> {
+        let mut reader = s.as_bytes();
+        let mut deserializer = Deserializer::from_buf_reader(&mut reader).strict(true);
+        T::deserialize(&mut deserializer)
+    }
+
+    #[test]
+    fn de_strict() {
+        let s = "*3\r\n:-23\r\n+Hello \r\n$0\r\n\r\n";
+        let result: RESPType = from_strict(s).unwrap();
+        assert_eq!(
+            result,
+            RESPType::Array(Some(vec![
+                RESPType::Integer(-23),
+                RESPType::SimpleString("Hello ".into()),
+                RESPType::BulkString(Some(Vec::new()))
+            ]))
+        );
+    }
+
+    #[test]
+    fn de_strict_violations() {
+        let cases = [
+            ("+OK\n", Violation::MissingCarriageReturn),
+            ("+O\rK\r\n", Violation::StrayCarriageReturn),
+            (":5 \r\n", Violation::Whitespace),
+            (":+5\r\n", Violation::PlusSign),
+            (":05\r\n", Violation::LeadingZero),
+            (":-0\r\n", Violation::LeadingZero),
+            ("*01\r\n:1\r\n", Violation::LeadingZero),
+            ("$-2\r\n", Violation::NegativeLength),
+        ];
+        for (s, violation) in cases.iter() {
+            let err = from_strict::<RESPType>(s).unwrap_err();
+            assert!(
+                matches!(err.inner(), Error::Strict(v) if v == violation),
+                "{:?}",
+                s
+            );
+            assert!(from_string::<RESPType>(s.to_string()).is_ok(), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn de_keeps_trailing_whitespace() {
+        let s = "*3\r\n+OK \t\r\n-ERR x \n:5 \r\n";
+        let expected = RESPType::Array(Some(vec![
+            RESPType::SimpleString(b"OK \t".to_vec()),
+            RESPType::Error(b"ERR x ".to_vec()),
+            RESPType::Integer(5),
+        ]));
+        assert_eq!(from_string::<RESPType>(s.to_string()).unwrap(), expected);
+        let (view, _) = crate::RESPRef::parse(s.as_bytes()).unwrap().unwrap();
+        assert_eq!(view.to_owned(), expected);
+        assert!(from_string::<()>("+OK \r\n".to_string()).is_err());
+    }
+
+    #[test]
+    fn de_error_position() {
+        let s = String::from("*3\r\n:1\r\n*2\r\n+OK\r\n:x\r\n");
+        let err = from_string::<RESPType>(s).unwrap_err();
+        let position = err.position().unwrap();
+        assert_eq!(position.offset, 17);
+        assert_eq!(position.path, vec![1, 1]);
+        assert_eq!(position.prefix, Some(b':'));
+        assert!(matches!(
+            &position.error,
+            Error::Syntax { expected: "integer", found } if found == "\"x\""
+        ));
+        assert_eq!(
+            err.to_string(),
+            "expected integer, found \"x\" at byte 17 in value \":\" at [1][1]"
+        );
+    }
+
+    #[test]
+    fn de_error_clone_and_eq() {
+        let err = from_string::<RESPType>(String::from("*1\r\n:x\r\n")).unwrap_err();
+        assert_eq!(err.clone(), err);
+        assert_ne!(err, Error::Eof);
+
+        let broken = io::Error::new(io::ErrorKind::BrokenPipe, "write");
+        let err = Error::from(broken);
+        assert_eq!(err.clone(), err);
+        assert_eq!(err, io::Error::from(io::ErrorKind::BrokenPipe).into());
+        assert_ne!(err, io::Error::from(io::ErrorKind::TimedOut).into());
+        assert_eq!(into_io_error(err.clone()).kind(), io::ErrorKind::BrokenPipe);
+        assert_eq!(into_io_error(err).to_string(), "write");
+    }
+
+    #[test]
+    fn de_unknown_prefix() {
+        let err = from_string::<RESPType>(String::from("!5\r\n")).unwrap_err();
+        assert_eq!(err.position().unwrap().prefix, Some(b'!'));
+        assert!(matches!(err.inner(), Error::Syntax { .. }));
+    }
+
+    #[test]
+    fn de_resync() {
+        let mut reader = &b"+OK\r\n!garbage\r\nmore\r\n:5\r\n:x\r\n:6\r\n"[..];
+        let mut deserializer = Deserializer::from_buf_reader(&mut reader);
+        let ok = RESPType::deserialize(&mut deserializer).unwrap();
+        assert_eq!(ok, RESPType::SimpleString("OK".into()));
+        assert!(RESPType::deserialize(&mut deserializer).is_err());
+        assert_eq!(deserializer.resync().unwrap(), 15);
+        let five = RESPType::deserialize(&mut deserializer).unwrap();
+        assert_eq!(five, RESPType::Integer(5));
+        assert!(RESPType::deserialize(&mut deserializer).is_err());
+        assert_eq!(deserializer.resync().unwrap(), 0);
+        let six = RESPType::deserialize(&mut deserializer).unwrap();
+        assert_eq!(six, RESPType::Integer(6));
+        assert_eq!(deserializer.offset(), 33);
+    }
+
+    #[test]
+    fn de_parse_integer() {
+        assert_eq!(parse_integer(b"0"), Some(0));
+        assert_eq!(parse_integer(b"+12"), Some(12));
+        assert_eq!(parse_integer(b"-9223372036854775808"), Some(i64::MIN));
+        assert_eq!(parse_integer(b"9223372036854775807"), Some(i64::MAX));
+        assert_eq!(parse_integer(b"9223372036854775808"), None);
+        for malformed in [&b""[..], b"-", b"+", b"1 2", b"0x10", b"--1"].iter() {
+            assert_eq!(parse_integer(malformed), None, "{:?}", malformed);
+        }
+    }
+
+    #[test]
+    fn de_lines_across_buffer_boundary() {
+        let input = &b"*3\r\n+a longer simple string\r\n:-1234567890\r\n$11\r\nhello world\r\n"[..];
+        let mut reader = BufReader::with_capacity(4, input);
+        let value: RESPType = from_buf_reader(&mut reader).unwrap();
+        assert_eq!(
+            value,
+            RESPType::Array(Some(vec![
+                RESPType::SimpleString(b"a longer simple string".to_vec()),
+                RESPType::Integer(-1234567890),
+                RESPType::BulkString(Some(b"hello world".to_vec())),
+            ]))
+        );
+    }
+
+    #[test]
+    fn de_long_header_line() {
+        // The line never ends, it has to be rejected before it is buffered
+        let input = io::Read::chain(&b":"[..], io::repeat(b'1'));
+        let mut reader = BufReader::with_capacity(4, input);
+        let err = from_buf_reader::<_, RESPType>(&mut reader).unwrap_err();
+        assert!(matches!(
+            err.inner(),
+            Error::Syntax {
+                expected: "shorter line",
+                ..
+            }
+        ));
+        let long = "*1\r\n:".to_string() + &"0".repeat(MAX_HEADER_LEN) + "\r\n";
+        assert!(from_string::<Vec<i64>>(long).is_err());
+        let long = "$".to_string() + &"0".repeat(MAX_HEADER_LEN - 3) + "\r\n\r\n";
+        assert_eq!(from_string::<String>(long).unwrap(), "");
+        let long = "+".to_string() + &"x".repeat(MAX_HEADER_LEN) + "\r\n";
+        let mut reader = BufReader::with_capacity(4, long.as_bytes());
+        let string: String = from_buf_reader(&mut reader).unwrap();
+        assert_eq!(string.len(), MAX_HEADER_LEN);
+    }
+
+    #[test]
+    fn de_simple_string_allocation() {
+        let mut string = None;
+        let count = crate::ser::tests::allocations(|| {
+            string = Some(from_buf_reader::<_, String>(&mut &b"+OK\r\n"[..]).unwrap());
+        });
+        assert_eq!(string.unwrap(), "OK");
+        assert_eq!(count, 1);
+    }
+
+    /// Unwrap nested one-element arrays without recursion, returns the
+    /// depth and the innermost value
+    fn unnest(mut value: RESPType) -> (usize, RESPType) {
+        let mut depth = 0;
+        loop {
+            match value {
+                RESPType::Array(Some(mut items)) if items.len() == 1 => {
+                    value = items.pop().unwrap();
+                    depth += 1;
+                }
+                value => return (depth, value),
+            }
+        }
+    }
+
+    #[test]
+    fn de_max_depth() {
+        let input = "*1\r\n".repeat(DEFAULT_MAX_DEPTH + 1) + ":1\r\n";
+        let err = from_string::<RESPType>(input.clone()).unwrap_err();
+        assert!(matches!(err.inner(), Error::DepthLimit(DEFAULT_MAX_DEPTH)));
+        assert_eq!(err.position().unwrap().path.len(), DEFAULT_MAX_DEPTH);
+        assert!(from_string::<Vec<Vec<Vec<i64>>>>(input).is_err());
+
+        let input = "*1\r\n*2\r\n:1\r\n*0\r\n";
+        for (max_depth, ok) in [(3, true), (2, false)].iter() {
+            let mut reader = input.as_bytes();
+            let mut deserializer = Deserializer::from_buf_reader(&mut reader).max_depth(*max_depth);
+            assert_eq!(RESPType::deserialize(&mut deserializer).is_ok(), *ok);
+        }
+    }
+
+    #[test]
+    fn de_read_value() {
+        let depth = 10_000;
+        let input = "*1\r\n".repeat(depth) + "*2\r\n:1\r\n$1\r\na\r\n:2\r\n*1\r\n:x\r\n";
+        let mut reader = input.as_bytes();
+        let mut deserializer = Deserializer::from_buf_reader(&mut reader).max_depth(usize::MAX);
+        let (nested, inner) = unnest(deserializer.read_value().unwrap());
+        assert_eq!(nested, depth);
+        assert_eq!(
+            inner,
+            RESPType::Array(Some(vec![
+                RESPType::Integer(1),
+                RESPType::BulkString(Some(b"a".to_vec()))
+            ]))
+        );
+        assert_eq!(deserializer.read_value().unwrap(), RESPType::Integer(2));
+        let err = deserializer.read_value().unwrap_err();
+        assert_eq!(err.position().unwrap().path, vec![0]);
+        assert!(deserializer.path.is_empty());
+
+        let mut reader = "*1\r\n*1\r\n*0\r\n".as_bytes();
+        let mut deserializer = Deserializer::from_buf_reader(&mut reader).max_depth(2);
+        assert!(matches!(
+            deserializer.read_value().unwrap_err().inner(),
+            Error::DepthLimit(2)
+        ));
+    }
+
+    #[test]
+    fn de_read_bulk_stream() {
+        use std::io::Read;
+
+        let input = &b"$11\r\nhello world\r\n$5\r\nabcde\r\n$-1\r\n:1\r\n$3\r\nabcXX"[..];
+        let mut reader = BufReader::with_capacity(4, input);
+        let mut deserializer = Deserializer::from_buf_reader(&mut reader);
+        let mut payload = Vec::new();
+        let mut bulk = deserializer.read_bulk_stream().unwrap().unwrap();
+        assert_eq!(bulk.len(), 11);
+        bulk.read_to_end(&mut payload).unwrap();
+        drop(bulk);
+        assert_eq!(payload, b"hello world");
+        assert_eq!(deserializer.offset(), 18);
+
+        let mut bulk = deserializer.read_bulk_stream().unwrap().unwrap();
+        let mut start = [0u8; 2];
+        bulk.read_exact(&mut start).unwrap();
+        assert_eq!(&start, b"ab");
+        drop(bulk);
+        assert_eq!(deserializer.offset(), 29);
+
+        assert!(deserializer.read_bulk_stream().unwrap().is_none());
+        assert_eq!(
+            RESPType::deserialize(&mut deserializer).unwrap(),
+            RESPType::Integer(1)
+        );
+        let mut bulk = deserializer.read_bulk_stream().unwrap().unwrap();
+        let err = bulk.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut reader = &b":1\r\n"[..];
+        let mut deserializer = Deserializer::from_buf_reader(&mut reader);
+        assert!(deserializer.read_bulk_stream().is_err());
+    }
+
+    #[test]
+    fn de_read_array_stream() {
+        let input = b"*4\r\n:1\r\n$3\r\nfoo\r\n*2\r\n+a\r\n*1\r\n$-1\r\n-ERR x\r\n:5\r\n";
+        let mut reader = BufReader::with_capacity(4, &input[..]);
+        let mut deserializer = Deserializer::from_buf_reader(&mut reader);
+        let mut array = deserializer.read_array_stream().unwrap().unwrap();
+        assert_eq!(array.len(), 4);
+        assert_eq!(array.next_element::<i64>().unwrap(), Some(1));
+        assert_eq!(
+            array.next().unwrap().unwrap(),
+            RESPType::BulkString(Some(b"foo".to_vec()))
+        );
+        // The rest is skipped on drop
+        drop(array);
+        assert_eq!(
+            RESPType::deserialize(&mut deserializer).unwrap(),
+            RESPType::Integer(5)
+        );
+
+        let mut reader = &b"*3\r\n:1\r\n:2\r\n:3\r\n*-1\r\n*1\r\n?\r\n"[..];
+        let mut deserializer = Deserializer::from_buf_reader(&mut reader);
+        let array = deserializer.read_array_stream().unwrap().unwrap();
+        let items: Result<Vec<RESPType>> = array.collect();
+        assert_eq!(items.unwrap().len(), 3);
+        assert!(deserializer.read_array_stream().unwrap().is_none());
+        let mut array = deserializer.read_array_stream().unwrap().unwrap();
+        let err = array.next().unwrap().unwrap_err();
+        assert_eq!(err.position().unwrap().path, vec![0]);
+        assert!(array.next().is_none());
+    }
+
+    #[test]
+    fn de_skip_value() {
+        let input = "*3\r\n$3\r\nfoo\r\n*1\r\n".to_string()
+            + &"*1\r\n".repeat(10_000)
+            + ":1\r\n-ERR\r\n+next\r\n";
+        let mut reader = input.as_bytes();
+        let mut deserializer = Deserializer::from_buf_reader(&mut reader);
+        deserializer.skip_value().unwrap();
+        assert_eq!(
+            RESPType::deserialize(&mut deserializer).unwrap(),
+            RESPType::SimpleString(b"next".to_vec())
+        );
+        let mut reader = &b"*3\r\n:1\r\n$3\r\nfoo\r\n"[..];
+        let mut deserializer = Deserializer::from_buf_reader(&mut reader);
+        assert!(deserializer.skip_value().is_err());
+        let mut reader = &b"*2\r\n:1\r\n*2\r\n+a\r\n?\r\n"[..];
+        let mut deserializer = Deserializer::from_buf_reader(&mut reader);
+        let err = deserializer.skip_value().unwrap_err();
+        assert_eq!(err.position().unwrap().path, [1, 1]);
+        assert!(deserializer.path.is_empty());
+    }
+
+    #[test]
+    fn de_ignored_any() {
+        let mut reader = &b"*2\r\n$100000\r\n"[..];
+        assert!(from_buf_reader::<_, de::IgnoredAny>(&mut reader).is_err());
+        let input = "*3\r\n$5\r\nhello\r\n*1\r\n-ERR\r\n:1\r\n+next\r\n".to_string();
+        let mut reader = input.as_bytes();
+        let mut deserializer = Deserializer::from_buf_reader(&mut reader);
+        de::IgnoredAny::deserialize(&mut deserializer).unwrap();
+        let next = String::deserialize(&mut deserializer).unwrap();
+        assert_eq!(next, "next");
+    }
+
+    #[test]
+    fn de_struct_skips_unknown_fields() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Info {
+            length: i64,
+            name: String,
+        }
+
+        let input = "*8\r\n$6\r\nlength\r\n:5\r\n$6\r\ngroups\r\n*2\r\n$1\r\na\r\n*0\r\n\
+                     +name\r\n$6\r\nstream\r\n$9\r\nnew-field\r\n$5\r\n\x00\x01\x02\x03\x04\r\n";
+        let info: Info = from_string(input.to_string()).unwrap();
+        assert_eq!(
+            info,
+            Info {
+                length: 5,
+                name: "stream".to_owned()
+            }
+        );
+        let err = from_string::<Info>("*1\r\n:1\r\n".to_string()).unwrap_err();
+        assert!(err.to_string().contains("even number of elements"));
+        assert!(from_string::<Info>("*2\r\n+name\r\n+x\r\n".to_string()).is_err());
+    }
+
+    #[test]
+    fn de_tuple() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Scan(String, Vec<String>);
+
+        let input = "*2\r\n$4\r\nlist\r\n$5\r\nvalue\r\n";
+        let blpop: (String, String) = from_string(input.to_string()).unwrap();
+        assert_eq!(blpop, ("list".to_owned(), "value".to_owned()));
+        let input = "*2\r\n$1\r\n0\r\n*2\r\n$1\r\na\r\n$1\r\nb\r\n";
+        let scan: Scan = from_string(input.to_string()).unwrap();
+        assert_eq!(
+            scan,
+            Scan("0".to_owned(), vec!["a".to_owned(), "b".to_owned()])
+        );
+        let bytes: [u8; 3] = from_string("$3\r\nabc\r\n".to_string()).unwrap();
+        assert_eq!(&bytes, b"abc");
+    }
+
+    #[test]
+    fn de_tuple_numbers() {
+        let pair: (i32, u8) = from_string("*2\r\n:-7\r\n$3\r\n255\r\n".to_string()).unwrap();
+        assert_eq!(pair, (-7, 255));
+        let array: [u16; 2] = from_string("*2\r\n:1\r\n+2\r\n".to_string()).unwrap();
+        assert_eq!(array, [1, 2]);
+        let big: u64 = from_string("$20\r\n18446744073709551615\r\n".to_string()).unwrap();
+        assert_eq!(big, u64::MAX);
+        let input = "*4\r\n$1\r\na\r\n$3\r\n1.5\r\n$1\r\nb\r\n$4\r\n-inf\r\n";
+        let scores: Pairs<String, f64> = from_string(input.to_string()).unwrap();
+        assert_eq!(
+            scores,
+            Pairs(vec![
+                ("a".to_owned(), 1.5),
+                ("b".to_owned(), f64::NEG_INFINITY)
+            ])
+        );
+        let value: RESPType = from_string(input.to_string()).unwrap();
+        assert_eq!(Pairs::<String, f64>::deserialize(&value).unwrap(), scores);
+        assert_eq!(Pairs::<String, f64>::deserialize(value).unwrap(), scores);
+
+        let err = from_string::<(i32, i8)>("*2\r\n:1\r\n:300\r\n".to_string()).unwrap_err();
+        assert_eq!(err.position().unwrap().path, [1]);
+        assert!(
+            err.to_string().contains("invalid value: integer `300`"),
+            "{}",
+            err
+        );
+        assert!(from_string::<u32>(":-1\r\n".to_string()).is_err());
+        assert!(from_string::<i32>("$3\r\n1.5\r\n".to_string()).is_err());
+        assert!(from_string::<f64>("$3\r\nabc\r\n".to_string()).is_err());
+    }
+
+    #[test]
+    fn de_tuple_length_mismatch() {
+        let mut reader = &b"*3\r\n:1\r\n*1\r\n:2\r\n:3\r\n+next\r\n"[..];
+        let mut deserializer = Deserializer::from_buf_reader(&mut reader);
+        let err = <(i64, i64)>::deserialize(&mut deserializer).unwrap_err();
+        assert!(matches!(
+            err.inner(),
+            Error::LengthMismatch {
+                expected: 2,
+                found: 3,
+                bulk_string: false
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "expected an array of 2 elements, found 3 at byte 0 in value \"*\""
+        );
+        let next = String::deserialize(&mut deserializer).unwrap();
+        assert_eq!(next, "next");
+        let err = from_string::<(i64,)>("*2\r\n:1\r\n*1\r\n:x\r\n".to_string()).unwrap_err();
+        assert!(matches!(err.inner(), Error::Syntax { .. }), "{}", err);
+        assert_eq!(err.position().unwrap().path, [1, 0]);
+        let err = from_string::<[u8; 2]>("$3\r\nabc\r\n".to_string()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected a bulk string of 2 bytes, found 3 at byte 0 in value \"$\""
+        );
+    }
+
+    #[test]
+    fn de_option() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Entry {
+            name: String,
+            ttl: Option<i64>,
+        }
+
+        assert_eq!(
+            from_string::<Option<String>>("$-1\r\n".to_string()).unwrap(),
+            None
+        );
+        assert_eq!(
+            from_string::<Option<String>>("$2\r\nhi\r\n".to_string()).unwrap(),
+            Some("hi".to_owned())
+        );
+        let input = "*3\r\n:1\r\n$-1\r\n*-1\r\n";
+        let items: Vec<Option<i64>> = from_string(input.to_string()).unwrap();
+        assert_eq!(items, [Some(1), None, None]);
+        let items: Option<Vec<i64>> = from_string("*1\r\n:2\r\n".to_string()).unwrap();
+        assert_eq!(items, Some(vec![2]));
+        let input = "*4\r\n+name\r\n$1\r\na\r\n+ttl\r\n$-1\r\n";
+        let entry: Entry = from_string(input.to_string()).unwrap();
+        assert_eq!(
+            entry,
+            Entry {
+                name: "a".to_owned(),
+                ttl: None
+            }
+        );
+        let value: RESPType = from_string(input.to_string()).unwrap();
+        assert_eq!(Entry::deserialize(&value).unwrap(), entry);
+        assert_eq!(Entry::deserialize(value).unwrap(), entry);
+
+        let err = from_string::<Option<i64>>("*2\r\n:1\r\n".to_string()).unwrap_err();
+        assert_eq!(err.position().unwrap().offset, 0);
+        let err = from_string::<Option<Vec<i64>>>("*2\r\n:1\r\n:x\r\n".to_string()).unwrap_err();
+        assert_eq!(err.position().unwrap().offset, 8);
+    }
+
+    #[test]
+    fn de_enum() {
+        #[derive(Debug, Deserialize)]
+        enum Kind {
+            String,
+        }
+
+        let mut reader = &b"*1\r\n+string\r\n:5\r\n"[..];
+        let mut deserializer = Deserializer::from_buf_reader(&mut reader);
+        let err = Kind::deserialize(&mut deserializer).unwrap_err();
+        assert_eq!(err.to_string(), "enum values are not supported");
+        assert_eq!(i64::deserialize(&mut deserializer).unwrap(), 5);
+        let value = RESPType::SimpleString(b"String".to_vec());
+        assert_eq!(Kind::deserialize(&value).unwrap_err(), err);
+        assert_eq!(Kind::deserialize(value).unwrap_err(), err);
+    }
+
+    #[test]
+    fn de_newtype_and_unit() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct UserId(u64);
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Name(String);
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Ack;
+
+        let id: UserId = from_string(":42\r\n".to_string()).unwrap();
+        assert_eq!(id, UserId(42));
+        assert!(from_string::<UserId>(":-1\r\n".to_string()).is_err());
+        let name: Name = from_string("$5\r\nalice\r\n".to_string()).unwrap();
+        assert_eq!(name, Name("alice".to_owned()));
+        from_string::<()>("+OK\r\n".to_string()).unwrap();
+        assert_eq!(from_string::<Ack>("+OK\r\n".to_string()).unwrap(), Ack);
+        let err = from_string::<()>("+QUEUED\r\n".to_string()).unwrap_err();
+        assert!(matches!(err.inner(), Error::Syntax { .. }), "{}", err);
+        assert!(from_string::<Ack>(":1\r\n".to_string()).is_err());
+    }
+
+    #[test]
+    fn de_bool_and_char() {
+        assert!(from_string::<bool>(":1\r\n".to_string()).unwrap());
+        assert!(!from_string::<bool>(":0\r\n".to_string()).unwrap());
+        assert!(from_string::<bool>("#t\r\n".to_string()).unwrap());
+        assert!(!from_string::<bool>("#f\r\n".to_string()).unwrap());
+        for invalid in [":2\r\n", "#x\r\n", "+true\r\n"].iter() {
+            assert!(
+                from_string::<bool>(invalid.to_string()).is_err(),
+                "{}",
+                invalid
+            );
+        }
+        assert_eq!(from_string::<char>("+a\r\n".to_string()).unwrap(), 'a');
+        assert_eq!(
+            from_string::<char>("$3\r\n\u{20ac}\r\n".to_string()).unwrap(),
+            '\u{20ac}'
+        );
+        let err = from_string::<char>("$2\r\nab\r\n".to_string()).unwrap_err();
+        assert!(err.to_string().contains("invalid length 2"), "{}", err);
+        assert!(from_string::<char>("+\r\n".to_string()).is_err());
+    }
+
+    #[test]
+    fn de_resp3_booleans() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Flags {
+            exists: bool,
+        }
+
+        let input = "*4\r\n+exists\r\n#t\r\n+unknown\r\n#f\r\n";
+        let flags: Flags = from_string(input.to_string()).unwrap();
+        assert_eq!(flags, Flags { exists: true });
+        assert_eq!(
+            crate::frame_len(input.as_bytes()).unwrap(),
+            Some(input.len())
+        );
+        let value: RESPType = from_string(input.to_string()).unwrap();
+        assert_eq!(Flags::deserialize(&value).unwrap(), flags);
+        assert_eq!(
+            from_string::<RESPType>("#f\r\n".to_string()).unwrap(),
+            RESPType::Integer(0)
+        );
+        assert!(from_string::<RESPType>("#x\r\n".to_string()).is_err());
+    }
+
+    #[test]
+    fn de_bulk_length_is_not_preallocated() {
+        struct NoopHandler;
+        impl Handler for NoopHandler {}
+
+        let input = "$1099511627776\r\nabc".to_string();
+        let err = from_string::<RESPType>(input.clone()).unwrap_err();
+        assert!(matches!(err.inner(), Error::Eof), "{}", err);
+        assert!(from_string::<Vec<u8>>(input.clone()).is_err());
+        let mut reader = BufReader::with_capacity(4, input.as_bytes());
+        let mut deserializer = Deserializer::from_buf_reader(&mut reader);
+        assert!(deserializer.parse_events(&mut NoopHandler).is_err());
+        assert_eq!(deserializer.offset(), input.len() as u64);
+    }
+
+    #[test]
+    fn de_exact_type_tuple_variant() {
+        /// Asks for the exact type of a value as a tuple variant
+        #[derive(Debug)]
+        struct TupleVariant;
+
+        impl<'de> Deserialize<'de> for TupleVariant {
+            fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+            where
+                D: de::Deserializer<'de>,
+            {
+                struct EnumVisitor;
+
+                impl<'de> Visitor<'de> for EnumVisitor {
+                    type Value = TupleVariant;
+
+                    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                        formatter.write_str("a value")
+                    }
+
+                    fn visit_enum<A>(self, data: A) -> std::result::Result<TupleVariant, A::Error>
+                    where
+                        A: EnumAccess<'de>,
+                    {
+                        let (_, variant): (u32, _) = data.variant()?;
+                        variant.tuple_variant(2, de::IgnoredAny)?;
+                        Ok(TupleVariant)
+                    }
+                }
+
+                deserializer.deserialize_newtype_struct(RESP_TYPE_TOKEN, EnumVisitor)
+            }
+        }
+
+        let err = from_string::<TupleVariant>(":1\r\n".to_string()).unwrap_err();
+        assert!(
+            err.to_string().contains("expected tuple variant"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn de_bulk_string_terminator() {
+        for input in ["$2\r\nab\n", "$2\r\nab\r", "$2\r\nabc"].iter() {
+            let err = from_string::<RESPType>(input.to_string()).unwrap_err();
+            assert!(
+                matches!(
+                    err.inner(),
+                    Error::Syntax {
+                        expected: "CRLF after bulk string",
+                        ..
+                    }
+                ),
+                "{:?}: {}",
+                input,
+                err
+            );
+        }
+        let err = from_string::<RESPType>("$2\r\nab".to_string()).unwrap_err();
+        assert!(matches!(err.inner(), Error::Eof));
+    }
 }
@@ -1,18 +1,136 @@
 use std::fmt::{self, Display};
 use std::io;
+use std::sync::Arc;
 
 use serde::{de, ser};
 use std::string::FromUtf8Error;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug)]
 pub enum Error {
     Message(String),
     FromUtf8(String),
-    Syntax,
-    Io(String),
+    /// Input doesn't match the protocol
+    Syntax {
+        expected: &'static str,
+        found: String,
+    },
+    /// I/O error of the reader or writer, compared by its kind
+    Io(Arc<io::Error>),
     Eof,
+    InvalidSimpleString,
+    Strict(Violation),
+    /// Array or bulk string read as a tuple has a different length
+    LengthMismatch {
+        expected: usize,
+        found: usize,
+        /// The value was a bulk string, its length is in bytes
+        bulk_string: bool,
+    },
+    /// Arrays are nested deeper than the limit of `de::Deserializer`
+    DepthLimit(usize),
+    /// Error raised by `de::Deserializer`, with its position in the stream
+    At(Box<Position>),
+}
+
+impl Error {
+    pub(crate) fn syntax(expected: &'static str, found: &[u8]) -> Self {
+        Error::Syntax {
+            expected,
+            found: escape(found),
+        }
+    }
+
+    /// Value of a Rust type which the serializers don't handle
+    pub(crate) fn unsupported(what: &str) -> Self {
+        Error::Message(format!("{} values are not supported", what))
+    }
+
+    /// Error itself, without its position in the stream
+    pub fn inner(&self) -> &Error {
+        match self {
+            Error::At(position) => position.error.inner(),
+            error => error,
+        }
+    }
+
+    /// Position in the stream, if the error was raised by `de::Deserializer`
+    pub fn position(&self) -> Option<&Position> {
+        match self {
+            Error::At(position) => Some(position),
+            _ => None,
+        }
+    }
+}
+
+/// Where `de::Deserializer` has met an error
+#[derive(Clone, Debug, PartialEq)]
+pub struct Position {
+    /// Offset of the first byte of the value with the error
+    pub offset: u64,
+    /// Indexes of the value in nested arrays, empty for the top-level value
+    pub path: Vec<usize>,
+    /// Prefix byte of the value, `None` if it couldn't be read
+    pub prefix: Option<u8>,
+    pub error: Error,
+}
+
+impl Display for Position {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{} at byte {}", self.error, self.offset)?;
+        if let Some(prefix) = self.prefix {
+            write!(formatter, " in value {}", escape(&[prefix]))?;
+        }
+        if !self.path.is_empty() {
+            formatter.write_str(" at ")?;
+            for index in &self.path {
+                write!(formatter, "[{}]", index)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Quote bytes for error messages, escaping everything except printable ASCII
+fn escape(bytes: &[u8]) -> String {
+    let mut escaped = String::with_capacity(bytes.len() + 2);
+    escaped.push('"');
+    for &byte in bytes {
+        escaped.extend(std::ascii::escape_default(byte).map(char::from));
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// Wire format rule broken in strict mode of `de::Deserializer`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Violation {
+    /// Line is terminated by LF without CR
+    MissingCarriageReturn,
+    /// Line contains CR which is not followed by LF
+    StrayCarriageReturn,
+    /// Integer or length is surrounded by whitespace
+    Whitespace,
+    /// Integer or length has an explicit `+` sign
+    PlusSign,
+    /// Integer or length has leading zeros or is a negative zero
+    LeadingZero,
+    /// Length is negative, but not `-1`
+    NegativeLength,
+}
+
+impl Display for Violation {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(match self {
+            Violation::MissingCarriageReturn => "line is terminated by LF without CR",
+            Violation::StrayCarriageReturn => "line contains CR which is not followed by LF",
+            Violation::Whitespace => "integer is surrounded by whitespace",
+            Violation::PlusSign => "integer has an explicit plus sign",
+            Violation::LeadingZero => "integer has leading zeros",
+            Violation::NegativeLength => "length is negative, but not -1",
+        })
+    }
 }
 
 impl ser::Error for Error {
@@ -32,20 +150,95 @@ impl Display for Error {
         match self {
             Error::Message(msg) => formatter.write_str(msg),
             Error::FromUtf8(msg) => formatter.write_str(msg),
-            Error::Io(msg) => formatter.write_str(msg),
+            Error::Io(err) => Display::fmt(err, formatter),
             Error::Eof => formatter.write_str("unexpected end of input"),
-            Error::Syntax => formatter.write_str("unexpected symbols"),
+            Error::Syntax { expected, found } => {
+                write!(formatter, "expected {}, found {}", expected, found)
+            }
+            Error::InvalidSimpleString => {
+                formatter.write_str("simple string or error contains CR or LF")
+            }
+            Error::Strict(violation) => write!(formatter, "strict mode: {}", violation),
+            Error::LengthMismatch {
+                expected,
+                found,
+                bulk_string: false,
+            } => write!(
+                formatter,
+                "expected an array of {} elements, found {}",
+                expected, found
+            ),
+            Error::LengthMismatch {
+                expected,
+                found,
+                bulk_string: true,
+            } => write!(
+                formatter,
+                "expected a bulk string of {} bytes, found {}",
+                expected, found
+            ),
+            Error::DepthLimit(limit) => {
+                write!(formatter, "arrays are nested deeper than {} levels", limit)
+            }
+            Error::At(position) => Display::fmt(position, formatter),
+        }
+    }
+}
+
+impl PartialEq for Error {
+    fn eq(&self, other: &Error) -> bool {
+        match (self, other) {
+            (Error::Message(a), Error::Message(b)) => a == b,
+            (Error::FromUtf8(a), Error::FromUtf8(b)) => a == b,
+            (
+                Error::Syntax { expected, found },
+                Error::Syntax {
+                    expected: other_expected,
+                    found: other_found,
+                },
+            ) => expected == other_expected && found == other_found,
+            (Error::Io(a), Error::Io(b)) => a.kind() == b.kind(),
+            (Error::Eof, Error::Eof) => true,
+            (Error::InvalidSimpleString, Error::InvalidSimpleString) => true,
+            (Error::Strict(a), Error::Strict(b)) => a == b,
+            (
+                Error::LengthMismatch {
+                    expected,
+                    found,
+                    bulk_string,
+                },
+                Error::LengthMismatch {
+                    expected: other_expected,
+                    found: other_found,
+                    bulk_string: other_bulk_string,
+                },
+            ) => {
+                expected == other_expected
+                    && found == other_found
+                    && bulk_string == other_bulk_string
+            }
+            (Error::DepthLimit(a), Error::DepthLimit(b)) => a == b,
+            (Error::At(a), Error::At(b)) => a == b,
+            _ => false,
         }
     }
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(&**err),
+            Error::At(position) => Some(&position.error),
+            _ => None,
+        }
+    }
+}
 
 impl From<io::Error> for Error {
     fn from(e: io::Error) -> Self {
         match e.kind() {
             io::ErrorKind::UnexpectedEof => Error::Eof,
-            _ => Error::Io(format!("{:?}", e)),
+            _ => Error::Io(Arc::new(e)),
         }
     }
 }
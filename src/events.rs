@@ -0,0 +1,150 @@
+use crate::Result;
+
+/// Callbacks for the values read by `de::Deserializer::parse_events`, in the
+/// order they appear in the stream. Strings are borrowed from the reader's
+/// buffer where possible.
+///
+/// Every callback does nothing by default. Returning an error stops parsing.
+pub trait Handler {
+    fn on_simple(&mut self, _value: &[u8]) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_error(&mut self, _value: &[u8]) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_integer(&mut self, _value: i64) -> Result<()> {
+        Ok(())
+    }
+
+    /// RESP3 boolean, handed to `on_integer` as 1 or 0 by default
+    fn on_bool(&mut self, value: bool) -> Result<()> {
+        self.on_integer(value as i64)
+    }
+
+    fn on_bulk(&mut self, _value: &[u8]) -> Result<()> {
+        Ok(())
+    }
+
+    /// Null bulk string, or null array when `array` is set
+    fn on_null(&mut self, _array: bool) -> Result<()> {
+        Ok(())
+    }
+
+    /// Array of `len` elements, which are followed by `on_array_end`
+    fn on_array_start(&mut self, _len: usize) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_array_end(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::de::{Deserializer, DEFAULT_MAX_DEPTH};
+    use crate::Error;
+    use std::io::BufReader;
+
+    /// Writes the values back in the wire format
+    struct Transcoder(Vec<u8>);
+
+    impl Handler for Transcoder {
+        fn on_simple(&mut self, value: &[u8]) -> Result<()> {
+            self.0.push(b'+');
+            self.0.extend_from_slice(value);
+            self.0.extend_from_slice(b"\r\n");
+            Ok(())
+        }
+
+        fn on_error(&mut self, value: &[u8]) -> Result<()> {
+            self.0.push(b'-');
+            self.0.extend_from_slice(value);
+            self.0.extend_from_slice(b"\r\n");
+            Ok(())
+        }
+
+        fn on_integer(&mut self, value: i64) -> Result<()> {
+            self.0
+                .extend_from_slice(format!(":{}\r\n", value).as_bytes());
+            Ok(())
+        }
+
+        fn on_bulk(&mut self, value: &[u8]) -> Result<()> {
+            self.0
+                .extend_from_slice(format!("${}\r\n", value.len()).as_bytes());
+            self.0.extend_from_slice(value);
+            self.0.extend_from_slice(b"\r\n");
+            Ok(())
+        }
+
+        fn on_null(&mut self, array: bool) -> Result<()> {
+            self.0
+                .extend_from_slice(if array { b"*-1\r\n" } else { b"$-1\r\n" });
+            Ok(())
+        }
+
+        fn on_array_start(&mut self, len: usize) -> Result<()> {
+            self.0.extend_from_slice(format!("*{}\r\n", len).as_bytes());
+            Ok(())
+        }
+    }
+
+    /// Counts array ends and fails on errors
+    #[derive(Default)]
+    struct Validator {
+        arrays: usize,
+    }
+
+    impl Handler for Validator {
+        fn on_error(&mut self, _value: &[u8]) -> Result<()> {
+            Err(Error::Message("error reply".to_owned()))
+        }
+
+        fn on_array_end(&mut self) -> Result<()> {
+            self.arrays += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn events_transcode() {
+        let input = &b"*5\r\n+OK\r\n-ERR x\r\n:-7\r\n*3\r\n$11\r\nhello world\r\n$-1\r\n*0\r\n*-1\r\n$3\r\nfoo\r\n"[..];
+        let mut reader = BufReader::with_capacity(8, input);
+        let mut deserializer = Deserializer::from_buf_reader(&mut reader);
+        let mut transcoder = Transcoder(Vec::new());
+        deserializer.parse_events(&mut transcoder).unwrap();
+        deserializer.parse_events(&mut transcoder).unwrap();
+        assert_eq!(transcoder.0, input);
+    }
+
+    #[test]
+    fn events_stop_on_error() {
+        let mut reader = &b"*2\r\n*1\r\n*0\r\n*2\r\n:1\r\n-ERR x\r\n"[..];
+        let mut deserializer = Deserializer::from_buf_reader(&mut reader);
+        let mut validator = Validator::default();
+        let err = deserializer.parse_events(&mut validator).unwrap_err();
+        assert_eq!(err.position().unwrap().path, vec![1, 1]);
+        assert_eq!(validator.arrays, 2);
+    }
+
+    #[test]
+    fn events_depth_limit() {
+        let input = "*1\r\n".repeat(100_000) + ":1\r\n";
+        let mut reader = input.as_bytes();
+        let mut deserializer = Deserializer::from_buf_reader(&mut reader);
+        let err = deserializer
+            .parse_events(&mut Validator::default())
+            .unwrap_err();
+        assert!(matches!(err.inner(), Error::DepthLimit(DEFAULT_MAX_DEPTH)));
+
+        let mut reader = &b"*1\r\n*1\r\n*0\r\n"[..];
+        let mut deserializer = Deserializer::from_buf_reader(&mut reader).max_depth(3);
+        let mut validator = Validator::default();
+        deserializer.parse_events(&mut validator).unwrap();
+        assert_eq!(validator.arrays, 3);
+    }
+}
@@ -0,0 +1,180 @@
+use crate::de::DEFAULT_MAX_DEPTH;
+use crate::scan::{build, check_depth, frame_len, Token};
+use crate::ser::{Bytes as ByteSlice, ERROR_TOKEN, NULL_BULK_STRING_TOKEN, SIMPLE_STRING_TOKEN};
+use crate::{RESPType, Result};
+use bytes::{Bytes, BytesMut};
+use serde::ser::SerializeSeq;
+use serde::{Serialize, Serializer};
+
+/// Same as `RESPType`, but strings are `Bytes`: slices of the buffer they
+/// were decoded from, which are cloned without copying.
+///
+/// RESP3 booleans are decoded as integers, see `RESPType`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Frame {
+    SimpleString(Bytes),
+    Error(Bytes),
+    Integer(i64),
+    BulkString(Option<Bytes>),
+    Array(Option<Vec<Frame>>),
+}
+
+/// Split the first complete value off `buf` and decode it without copying
+/// strings. Returns `None` and leaves `buf` as it is if the value is not
+/// complete yet.
+///
+/// Arrays nested deeper than `DEFAULT_MAX_DEPTH` levels are rejected with
+/// `Error::DepthLimit`.
+pub fn decode(buf: &mut BytesMut) -> Result<Option<Frame>> {
+    decode_with_max_depth(buf, DEFAULT_MAX_DEPTH)
+}
+
+/// Same as `decode`, with arrays nested at most `max_depth` levels deep
+pub fn decode_with_max_depth(buf: &mut BytesMut, max_depth: usize) -> Result<Option<Frame>> {
+    let len = match frame_len(buf)? {
+        Some(len) => len,
+        None => return Ok(None),
+    };
+    check_depth(&buf[..len], max_depth)?;
+    let data = buf.split_to(len).freeze();
+    Ok(Some(build(
+        &data,
+        |token| match token {
+            Token::SimpleString(range) => Frame::SimpleString(data.slice(range)),
+            Token::Error(range) => Frame::Error(data.slice(range)),
+            Token::Integer(int) => Frame::Integer(int),
+            Token::BulkString(range) => Frame::BulkString(range.map(|range| data.slice(range))),
+            Token::Array(_) => Frame::Array(None),
+        },
+        |items| Frame::Array(Some(items)),
+    )))
+}
+
+impl From<Frame> for RESPType {
+    fn from(frame: Frame) -> Self {
+        match frame {
+            Frame::SimpleString(s) => RESPType::SimpleString(s.to_vec()),
+            Frame::Error(s) => RESPType::Error(s.to_vec()),
+            Frame::Integer(int) => RESPType::Integer(int),
+            Frame::BulkString(s) => RESPType::BulkString(s.map(|s| s.to_vec())),
+            Frame::Array(items) => {
+                RESPType::Array(items.map(|items| items.into_iter().map(Into::into).collect()))
+            }
+        }
+    }
+}
+
+impl From<RESPType> for Frame {
+    fn from(value: RESPType) -> Self {
+        match value {
+            RESPType::SimpleString(s) => Frame::SimpleString(s.into()),
+            RESPType::Error(s) => Frame::Error(s.into()),
+            RESPType::Integer(int) => Frame::Integer(int),
+            RESPType::BulkString(s) => Frame::BulkString(s.map(Into::into)),
+            RESPType::Array(items) => {
+                Frame::Array(items.map(|items| items.into_iter().map(Into::into).collect()))
+            }
+        }
+    }
+}
+
+impl Serialize for Frame {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Frame::SimpleString(s) => {
+                serializer.serialize_newtype_struct(SIMPLE_STRING_TOKEN, &ByteSlice(s))
+            }
+            Frame::Error(s) => serializer.serialize_newtype_struct(ERROR_TOKEN, &ByteSlice(s)),
+            Frame::Integer(int) => serializer.serialize_i64(*int),
+            Frame::BulkString(Some(s)) => serializer.serialize_bytes(s),
+            Frame::BulkString(None) => serializer.serialize_unit_struct(NULL_BULK_STRING_TOKEN),
+            Frame::Array(Some(items)) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Frame::Array(None) => serializer.serialize_none(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{to_vec, Error};
+
+    #[test]
+    fn frame_decode() {
+        let input = b"*3\r\n$5\r\nhello\r\n+OK\r\n*2\r\n:1\r\n$-1\r\n";
+        let mut buf = BytesMut::new();
+        for &byte in &input[..input.len() - 1] {
+            buf.extend_from_slice(&[byte]);
+            assert_eq!(decode(&mut buf).unwrap(), None);
+        }
+        buf.extend_from_slice(b"\n-ERR x\r\n");
+        let frame = decode(&mut buf).unwrap().unwrap();
+        assert_eq!(
+            frame,
+            Frame::Array(Some(vec![
+                Frame::BulkString(Some(Bytes::from_static(b"hello"))),
+                Frame::SimpleString(Bytes::from_static(b"OK")),
+                Frame::Array(Some(vec![Frame::Integer(1), Frame::BulkString(None)])),
+            ]))
+        );
+        assert_eq!(
+            decode(&mut buf).unwrap(),
+            Some(Frame::Error(Bytes::from_static(b"ERR x")))
+        );
+        assert!(buf.is_empty());
+        assert!(decode(&mut BytesMut::from(&b"?\r\n"[..])).is_err());
+        let mut buf = BytesMut::from(&b"#t\r\n"[..]);
+        let frame = decode(&mut buf).unwrap().unwrap();
+        assert_eq!(frame, Frame::Integer(1));
+        assert_eq!(to_vec(&frame).unwrap(), b":1\r\n");
+    }
+
+    #[test]
+    fn frame_depth_limit() {
+        let nested = "*1\r\n".repeat(1_000_000) + ":1\r\n";
+        let mut buf = BytesMut::from(nested.as_bytes());
+        assert!(matches!(
+            decode(&mut buf),
+            Err(Error::DepthLimit(DEFAULT_MAX_DEPTH))
+        ));
+        assert_eq!(buf.len(), nested.len());
+
+        let mut buf = BytesMut::from(&b"*1\r\n*1\r\n*0\r\n"[..]);
+        assert!(decode_with_max_depth(&mut buf, 2).is_err());
+        assert!(decode_with_max_depth(&mut buf, 3).unwrap().is_some());
+    }
+
+    #[test]
+    fn frame_shares_buffer() {
+        let mut buf = BytesMut::from(&b"$5\r\nhello\r\n"[..]);
+        let start = buf.as_ptr() as usize;
+        let payload = match decode(&mut buf).unwrap() {
+            Some(Frame::BulkString(Some(payload))) => payload,
+            frame => panic!("unexpected frame {:?}", frame),
+        };
+        assert_eq!(payload.as_ptr() as usize, start + 4);
+    }
+
+    #[test]
+    fn frame_into_resp_type() {
+        let value = RESPType::Array(Some(vec![
+            RESPType::SimpleString(b"OK".to_vec()),
+            RESPType::BulkString(Some(b"a\r\nb".to_vec())),
+            RESPType::Array(None),
+        ]));
+        let bytes = to_vec(&value).unwrap();
+        let frame = decode(&mut BytesMut::from(&bytes[..])).unwrap().unwrap();
+        assert_eq!(to_vec(&frame).unwrap(), bytes);
+        assert_eq!(RESPType::from(frame.clone()), value);
+        assert_eq!(Frame::from(value), frame);
+    }
+}
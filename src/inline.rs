@@ -0,0 +1,211 @@
+use crate::{Error, Result};
+use memchr::memchr;
+
+/// Split an inline command (`SET key "hello world"`) into arguments, using
+/// the same quoting rules as Redis (`sdssplitargs`):
+///
+/// * arguments are separated by whitespace, but unquoted ones end only at a
+///   space, `\t`, `\r` or `\n`;
+/// * double quotes allow `\xHH`, `\n`, `\r`, `\t`, `\b` and `\a` escapes,
+///   any other escaped character stands for itself;
+/// * single quotes allow only `\'`;
+/// * closing quote must be followed by whitespace or the end of the line;
+/// * the line ends at the first NUL byte, Redis reads it as a C string.
+pub fn split_args(line: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let line = &line[..memchr(0, line).unwrap_or(line.len())];
+    let mut args = Vec::new();
+    let mut i = 0;
+    loop {
+        while i < line.len() && is_space(line[i]) {
+            i += 1;
+        }
+        if i == line.len() {
+            return Ok(args);
+        }
+        let mut arg = Vec::new();
+        let mut quote = None;
+        loop {
+            let byte = match line.get(i) {
+                Some(&byte) => byte,
+                None if quote.is_some() => return Err(unbalanced(line)),
+                None => break,
+            };
+            match quote {
+                Some(b'"') if byte == b'\\' && i + 3 < line.len() && line[i + 1] == b'x' => {
+                    match (hex_digit(line[i + 2]), hex_digit(line[i + 3])) {
+                        (Some(high), Some(low)) => {
+                            arg.push(high << 4 | low);
+                            i += 3;
+                        }
+                        _ => {
+                            arg.push(b'x');
+                            i += 1;
+                        }
+                    }
+                }
+                Some(b'"') if byte == b'\\' && i + 1 < line.len() => {
+                    i += 1;
+                    arg.push(match line[i] {
+                        b'n' => b'\n',
+                        b'r' => b'\r',
+                        b't' => b'\t',
+                        b'b' => 0x08,
+                        b'a' => 0x07,
+                        other => other,
+                    });
+                }
+                Some(b'\'') if byte == b'\\' && line.get(i + 1) == Some(&b'\'') => {
+                    i += 1;
+                    arg.push(b'\'');
+                }
+                Some(closing) if byte == closing => {
+                    if line.get(i + 1).is_some_and(|&next| !is_space(next)) {
+                        return Err(unbalanced(line));
+                    }
+                    i += 1;
+                    break;
+                }
+                Some(_) => arg.push(byte),
+                None if ends_arg(byte) => break,
+                None if byte == b'"' || byte == b'\'' => quote = Some(byte),
+                None => arg.push(byte),
+            }
+            i += 1;
+        }
+        args.push(arg);
+    }
+}
+
+/// Whitespace as in C `isspace`
+fn is_space(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\n' | b'\r' | 0x0b | 0x0c)
+}
+
+/// Bytes which end an unquoted argument, a narrower set than `is_space`
+fn ends_arg(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\n' | b'\r')
+}
+
+fn hex_digit(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|digit| digit as u8)
+}
+
+fn unbalanced(line: &[u8]) -> Error {
+    Error::syntax("balanced quotes", line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::de::{from_buf_reader, Deserializer};
+    use crate::RESPType;
+    use serde::{de, Deserialize};
+
+    fn args(args: &[&str]) -> Vec<Vec<u8>> {
+        args.iter().map(|arg| arg.as_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn inline_split_args() {
+        assert_eq!(split_args(b"PING").unwrap(), args(&["PING"]));
+        assert_eq!(split_args(b"  \t").unwrap(), args(&[]));
+        assert_eq!(
+            split_args(br#"SET key "hello world""#).unwrap(),
+            args(&["SET", "key", "hello world"])
+        );
+        assert_eq!(
+            split_args(br#"SET "\x00\xffa\n\"" 'it\'s "x"'"#).unwrap(),
+            vec![
+                b"SET".to_vec(),
+                b"\x00\xffa\n\"".to_vec(),
+                b"it's \"x\"".to_vec()
+            ]
+        );
+        assert_eq!(
+            split_args(br#"a"b c"d"#).unwrap_err().to_string(),
+            r#"expected balanced quotes, found "a\"b c\"d""#
+        );
+        assert_eq!(
+            split_args(b"\x0bSET a\x0bb\x0c \"c\"\x0c").unwrap(),
+            args(&["SET", "a\x0bb\x0c", "c"])
+        );
+        assert_eq!(split_args(b"GET a\0b c").unwrap(), args(&["GET", "a"]));
+        assert!(split_args(b"GET \"a\0\"").is_err());
+        assert!(split_args(br#"SET "key"#).is_err());
+        assert!(split_args(b"SET 'key").is_err());
+    }
+
+    #[test]
+    fn inline_deserialize() {
+        let mut reader = &b"SET key \"hello world\"\r\n\r\n*1\r\n$4\r\nPING\r\nPING\r\n"[..];
+        let mut deserializer = Deserializer::from_buf_reader(&mut reader).inline_commands(true);
+        let set = RESPType::deserialize(&mut deserializer).unwrap();
+        assert_eq!(
+            set,
+            RESPType::Array(Some(vec![
+                RESPType::BulkString(Some(b"SET".to_vec())),
+                RESPType::BulkString(Some(b"key".to_vec())),
+                RESPType::BulkString(Some(b"hello world".to_vec())),
+            ]))
+        );
+        let multibulk = RESPType::deserialize(&mut deserializer).unwrap();
+        let inline = RESPType::deserialize(&mut deserializer).unwrap();
+        assert_eq!(inline, multibulk);
+    }
+
+    #[test]
+    fn inline_deserialize_typed() {
+        let mut reader = &b"GET \"my key\"\r\n"[..];
+        let mut deserializer = Deserializer::from_buf_reader(&mut reader).inline_commands(true);
+        let command = Vec::<String>::deserialize(&mut deserializer).unwrap();
+        assert_eq!(command, vec!["GET", "my key"]);
+        let mut reader = &b"PING\r\n"[..];
+        assert!(from_buf_reader::<_, RESPType>(&mut reader).is_err());
+    }
+
+    #[test]
+    fn inline_many_empty_lines() {
+        struct NoopHandler;
+        impl crate::events::Handler for NoopHandler {}
+
+        let input = "\r\n".repeat(1_000_000) + "PING\r\n";
+        let ping = RESPType::Array(Some(vec![RESPType::BulkString(Some(b"PING".to_vec()))]));
+        let mut reader = input.as_bytes();
+        let mut deserializer = Deserializer::from_buf_reader(&mut reader).inline_commands(true);
+        assert_eq!(RESPType::deserialize(&mut deserializer).unwrap(), ping);
+        let mut reader = input.as_bytes();
+        let mut deserializer = Deserializer::from_buf_reader(&mut reader).inline_commands(true);
+        assert_eq!(
+            Vec::<String>::deserialize(&mut deserializer).unwrap(),
+            ["PING"]
+        );
+        let mut reader = input.as_bytes();
+        let mut deserializer = Deserializer::from_buf_reader(&mut reader).inline_commands(true);
+        deserializer.parse_events(&mut NoopHandler).unwrap();
+        assert_eq!(deserializer.offset(), input.len() as u64);
+        let mut reader = input.as_bytes();
+        let mut deserializer = Deserializer::from_buf_reader(&mut reader).inline_commands(true);
+        de::IgnoredAny::deserialize(&mut deserializer).unwrap();
+        assert_eq!(deserializer.offset(), input.len() as u64);
+    }
+
+    #[test]
+    fn inline_any_first_byte() {
+        let mut reader = &b"$HOME\r\n-foo bar\r\n:1\r\n+OK\r\n*1\r\n$1\r\n:\r\n"[..];
+        let mut deserializer = Deserializer::from_buf_reader(&mut reader).inline_commands(true);
+        for expected in [&["$HOME"][..], &["-foo", "bar"], &[":1"], &["+OK"], &[":"]].iter() {
+            let command = Vec::<String>::deserialize(&mut deserializer).unwrap();
+            assert_eq!(&command, expected);
+        }
+        let mut reader = &b"-ERR x\r\n*1\r\nPING\r\n"[..];
+        let mut deserializer = Deserializer::from_buf_reader(&mut reader).inline_commands(true);
+        assert_eq!(
+            RESPType::deserialize(&mut deserializer).unwrap(),
+            RESPType::Array(Some(vec![
+                RESPType::BulkString(Some(b"-ERR".to_vec())),
+                RESPType::BulkString(Some(b"x".to_vec())),
+            ]))
+        );
+        assert!(RESPType::deserialize(&mut deserializer).is_err());
+    }
+}
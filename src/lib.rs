@@ -1,26 +1,82 @@
+pub mod cli;
 pub mod de;
 pub mod error;
+pub mod events;
+#[cfg(feature = "bytes")]
+pub mod frame;
+pub mod inline;
+pub mod pairs;
+pub mod scan;
 pub mod ser;
+pub mod value;
+pub mod view;
 
 pub use de::{from_buf_reader, from_string};
-pub use error::{Error, Result};
-pub use ser::to_string;
-use std::fmt::{Display, Formatter};
+pub use error::{Error, Position, Result, Violation};
+pub use pairs::Pairs;
+pub use scan::frame_len;
+pub use ser::{to_string, to_vec, to_writer, write_bulk_stream, write_value, Bytes, UnitRepr};
+use std::fmt::{Debug, Display, Formatter};
+pub use value::to_value;
+pub use view::RESPRef;
 
-#[derive(Debug, PartialOrd, PartialEq)]
+/// Value of the RESP2 protocol.
+///
+/// RESP3 booleans have no variant of their own: `#t` and `#f` are read as
+/// `Integer(1)` and `Integer(0)`, and written back as `:1` and `:0`. Encoding
+/// a decoded value doesn't always give back its original bytes.
+#[derive(PartialOrd, PartialEq)]
 pub enum RESPType {
-    SimpleString(String),
-    Error(String),
+    SimpleString(Vec<u8>),
+    Error(Vec<u8>),
     Integer(i64),
     BulkString(Option<Vec<u8>>),
     Array(Option<Vec<RESPType>>),
 }
 
+/// Byte strings longer than this are truncated in `Debug` output.
+const DEBUG_BYTES_LIMIT: usize = 128;
+
+/// Debug representation of bytes as an escaped byte string literal.
+struct DebugBytes<'a>(&'a [u8]);
+
+impl Debug for DebugBytes<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        let shown = &self.0[..self.0.len().min(DEBUG_BYTES_LIMIT)];
+        f.write_str("b\"")?;
+        for &byte in shown {
+            write!(f, "{}", std::ascii::escape_default(byte))?;
+        }
+        f.write_str("\"")?;
+        if shown.len() < self.0.len() {
+            write!(f, "... ({} bytes)", self.0.len())?;
+        }
+        Ok(())
+    }
+}
+
+impl Debug for RESPType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        match self {
+            RESPType::SimpleString(s) => {
+                f.debug_tuple("SimpleString").field(&DebugBytes(s)).finish()
+            }
+            RESPType::Error(err) => f.debug_tuple("Error").field(&DebugBytes(err)).finish(),
+            RESPType::Integer(int) => f.debug_tuple("Integer").field(int).finish(),
+            RESPType::BulkString(bytes) => f
+                .debug_tuple("BulkString")
+                .field(&bytes.as_deref().map(DebugBytes))
+                .finish(),
+            RESPType::Array(arr) => f.debug_tuple("Array").field(arr).finish(),
+        }
+    }
+}
+
 impl Display for RESPType {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
         match self {
-            RESPType::SimpleString(s) => write!(f, "{}", s)?,
-            RESPType::Error(err) => write!(f, "{}", err)?,
+            RESPType::SimpleString(s) => write!(f, "{}", String::from_utf8_lossy(s))?,
+            RESPType::Error(err) => write!(f, "{}", String::from_utf8_lossy(err))?,
             RESPType::Integer(int) => write!(f, "{}", int)?,
             RESPType::BulkString(possible_str) => match possible_str.clone() {
                 Some(s) => write!(f, "{}", String::from_utf8(s).unwrap_or_default())?,
@@ -36,3 +92,43 @@ impl Display for RESPType {
 }
 
 pub type RESP = RESPType;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_escaped_bytes() {
+        let value = RESPType::Array(Some(vec![
+            RESPType::SimpleString(b"OK".to_vec()),
+            RESPType::BulkString(Some(b"hello\x00\xff\"\r\n".to_vec())),
+            RESPType::BulkString(None),
+            RESPType::Integer(-1),
+        ]));
+        assert_eq!(
+            format!("{:?}", value),
+            r#"Array(Some([SimpleString(b"OK"), BulkString(Some(b"hello\x00\xff\"\r\n")), BulkString(None), Integer(-1)]))"#
+        );
+    }
+
+    #[test]
+    fn display_is_not_pretty() {
+        assert_eq!(RESPType::Integer(5).to_string(), "5");
+        assert_eq!(RESPType::SimpleString(b"OK".to_vec()).to_string(), "OK");
+        assert_eq!(RESPType::BulkString(None).to_string(), "None");
+        assert_eq!(
+            cli::pretty(&RESPType::Integer(5)).to_string(),
+            "(integer) 5"
+        );
+    }
+
+    #[test]
+    fn debug_truncated_bytes() {
+        let value = RESPType::BulkString(Some(vec![b'a'; 1000]));
+        let expected = format!(
+            "BulkString(Some(b\"{}\"... (1000 bytes)))",
+            "a".repeat(DEBUG_BYTES_LIMIT)
+        );
+        assert_eq!(format!("{:?}", value), expected);
+    }
+}
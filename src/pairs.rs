@@ -0,0 +1,84 @@
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeSeq, Serializer};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// Flat array read and written as pairs, like replies to `ZRANGE WITHSCORES`
+/// or `HGETALL`: `[a1, b1, a2, b2]` is `Pairs(vec![(a1, b1), (a2, b2)])`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Pairs<A, B>(pub Vec<(A, B)>);
+
+impl<A: Serialize, B: Serialize> Serialize for Pairs<A, B> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.0.len() * 2))?;
+        for (a, b) in &self.0 {
+            seq.serialize_element(a)?;
+            seq.serialize_element(b)?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de, A: Deserialize<'de>, B: Deserialize<'de>> Deserialize<'de> for Pairs<A, B> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct PairsVisitor<A, B>(PhantomData<(A, B)>);
+
+        impl<'de, A: Deserialize<'de>, B: Deserialize<'de>> Visitor<'de> for PairsVisitor<A, B> {
+            type Value = Pairs<A, B>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an array with an even number of elements")
+            }
+
+            fn visit_seq<S>(self, mut seq: S) -> Result<Self::Value, S::Error>
+            where
+                S: SeqAccess<'de>,
+            {
+                let mut pairs = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096) / 2);
+                while let Some(a) = seq.next_element()? {
+                    match seq.next_element()? {
+                        Some(b) => pairs.push((a, b)),
+                        None => return Err(de::Error::invalid_length(pairs.len() * 2 + 1, &self)),
+                    }
+                }
+                Ok(Pairs(pairs))
+            }
+        }
+
+        deserializer.deserialize_seq(PairsVisitor(PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{from_string, to_value, to_vec, RESPType};
+
+    #[test]
+    fn pairs_roundtrip() {
+        let input = "*4\r\n$3\r\none\r\n:1\r\n$3\r\ntwo\r\n:2\r\n";
+        let pairs: Pairs<String, i64> = from_string(input.to_string()).unwrap();
+        assert_eq!(
+            pairs,
+            Pairs(vec![("one".to_owned(), 1), ("two".to_owned(), 2)])
+        );
+        assert_eq!(to_vec(&pairs).unwrap(), input.as_bytes());
+        let value = to_value(&pairs).unwrap();
+        assert_eq!(Pairs::<String, i64>::deserialize(value).unwrap(), pairs);
+    }
+
+    #[test]
+    fn pairs_odd_length() {
+        let err =
+            from_string::<Pairs<String, i64>>("*3\r\n+a\r\n:1\r\n+b\r\n".to_string()).unwrap_err();
+        assert!(err.to_string().contains("invalid length 3"), "{}", err);
+        let value = RESPType::Array(Some(vec![RESPType::Integer(1)]));
+        assert!(Pairs::<i64, i64>::deserialize(value).is_err());
+    }
+}
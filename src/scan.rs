@@ -0,0 +1,262 @@
+use crate::de::{
+    parse_boolean, parse_integer, trim_line, DEFAULT_MAX_DEPTH, EXPECTED_PREFIX, MAX_HEADER_LEN,
+    PREFIXES,
+};
+use crate::{Error, Result};
+use memchr::memchr;
+use std::ops::Range;
+
+/// Header of a value found in a buffer, with the positions of its payload
+pub(crate) enum Token {
+    SimpleString(Range<usize>),
+    Error(Range<usize>),
+    Integer(i64),
+    BulkString(Option<Range<usize>>),
+    Array(Option<usize>),
+}
+
+/// Read the value header at `pos`, returns it with the position after the
+/// header and the bulk string payload. `None` if the buffer ends before it.
+pub(crate) fn next_token(buf: &[u8], pos: usize) -> Result<Option<(Token, usize)>> {
+    let prefix = match buf.get(pos) {
+        Some(&prefix) => prefix,
+        None => return Ok(None),
+    };
+    if !PREFIXES.contains(&prefix) {
+        return Err(Error::syntax(EXPECTED_PREFIX, &[prefix]));
+    }
+    let start = pos + 1;
+    // Lines of strings may be of any length, other lines hold a number
+    let line_end = match prefix {
+        b'+' | b'-' => buf.len(),
+        _ => buf.len().min(start + MAX_HEADER_LEN),
+    };
+    let end = match memchr(b'\n', &buf[start..line_end]) {
+        Some(i) => start + i + 1,
+        None if line_end < buf.len() => {
+            return Err(Error::syntax("shorter line", &buf[start..start + 32]))
+        }
+        None => return Ok(None),
+    };
+    let line = trim_line(&buf[start..end], false)?;
+    let line_range = start..start + line.len();
+    let token = match prefix {
+        b'+' => Token::SimpleString(line_range),
+        b'-' => Token::Error(line_range),
+        b':' => Token::Integer(parse_int(line)?),
+        // There is no boolean type in RESP2
+        b'#' => Token::Integer(parse_boolean(line)? as i64),
+        b'$' => match parse_length(line)? {
+            Some(len) => {
+                let payload_end = end.checked_add(len).filter(|&n| n <= usize::MAX - 2);
+                let payload_end = payload_end.ok_or_else(|| Error::syntax("length", line))?;
+                if buf.len() < payload_end + 2 {
+                    return Ok(None);
+                }
+                if &buf[payload_end..payload_end + 2] != b"\r\n" {
+                    return Err(Error::syntax(
+                        "CRLF after bulk string",
+                        &buf[payload_end..payload_end + 2],
+                    ));
+                }
+                return Ok(Some((
+                    Token::BulkString(Some(end..payload_end)),
+                    payload_end + 2,
+                )));
+            }
+            None => Token::BulkString(None),
+        },
+        b'*' => Token::Array(parse_length(line)?),
+        _ => return Err(Error::syntax(EXPECTED_PREFIX, &[prefix])),
+    };
+    Ok(Some((token, end)))
+}
+
+/// Length of the complete value at the start of `buf`, `None` if more input
+/// is needed.
+///
+/// The value is checked with the same rules as `de::Deserializer` uses by
+/// default, in a single pass, without allocation and without recursion into
+/// nested arrays. Pipelined values are split by calling it again on the
+/// rest of the buffer.
+///
+/// Unknown prefixes, and lines of integers and lengths longer than 64 KiB,
+/// fail as soon as they are in `buf`, so that garbage isn't buffered while
+/// waiting for a line end.
+pub fn frame_len(buf: &[u8]) -> Result<Option<usize>> {
+    let mut pos = 0;
+    let mut pending: usize = 1;
+    while pending > 0 {
+        pending -= 1;
+        let (token, end) = match next_token(buf, pos)? {
+            Some(token) => token,
+            None => return Ok(None),
+        };
+        if let Token::Array(Some(len)) = token {
+            pending = pending
+                .checked_add(len)
+                .ok_or_else(|| Error::syntax("length", &buf[pos..end]))?;
+        }
+        pos = end;
+    }
+    Ok(Some(pos))
+}
+
+/// Check that the complete value at the start of `buf`, which `frame_len` has
+/// accepted, has arrays nested at most `max_depth` levels deep, as
+/// `de::Deserializer::max_depth` does. Values built from deeper ones can't be
+/// dropped without overflowing the stack.
+///
+/// Nothing is allocated unless `max_depth` is above `DEFAULT_MAX_DEPTH`.
+pub(crate) fn check_depth(buf: &[u8], max_depth: usize) -> Result<()> {
+    // Elements left in each of the arrays which are open, kept on the stack up
+    // to the default limit
+    let mut stack = [0usize; DEFAULT_MAX_DEPTH];
+    let mut heap = Vec::new();
+    let remaining: &mut [usize] = if max_depth <= DEFAULT_MAX_DEPTH {
+        &mut stack
+    } else {
+        // Every array header takes at least 3 bytes
+        heap.resize(max_depth.min(buf.len() / 3 + 1), 0);
+        &mut heap
+    };
+    let mut depth = 0;
+    let mut pos = 0;
+    loop {
+        let (token, end) = match next_token(buf, pos) {
+            Ok(Some(token)) => token,
+            _ => panic!("value is not validated by frame_len"),
+        };
+        pos = end;
+        if let Token::Array(Some(len)) = token {
+            if depth >= max_depth {
+                return Err(Error::DepthLimit(max_depth));
+            }
+            if len > 0 {
+                remaining[depth] = len;
+                depth += 1;
+                continue;
+            }
+        }
+        loop {
+            if depth == 0 {
+                return Ok(());
+            }
+            remaining[depth - 1] -= 1;
+            if remaining[depth - 1] > 0 {
+                break;
+            }
+            depth -= 1;
+        }
+    }
+}
+
+/// Build a value from the complete value at the start of `buf`, which
+/// `frame_len` and `check_depth` have accepted. Arrays are assembled with
+/// `array` from their elements without recursion, other tokens are turned into
+/// values by `value`.
+pub(crate) fn build<T, V, A>(buf: &[u8], mut value: V, mut array: A) -> T
+where
+    V: FnMut(Token) -> T,
+    A: FnMut(Vec<T>) -> T,
+{
+    // Arrays which are being built, with their lengths
+    let mut arrays: Vec<(Vec<T>, usize)> = Vec::new();
+    let mut pos = 0;
+    loop {
+        let (token, end) = match next_token(buf, pos) {
+            Ok(Some(token)) => token,
+            _ => panic!("value is not validated by frame_len"),
+        };
+        pos = end;
+        let mut item = match token {
+            Token::Array(Some(len)) if len > 0 => {
+                arrays.push((Vec::with_capacity(len), len));
+                continue;
+            }
+            Token::Array(Some(_)) => array(Vec::new()),
+            token => value(token),
+        };
+        loop {
+            let (items, len) = match arrays.last_mut() {
+                Some(array) => array,
+                None => return item,
+            };
+            items.push(item);
+            if items.len() < *len {
+                break;
+            }
+            let (items, _) = arrays.pop().unwrap();
+            item = array(items);
+        }
+    }
+}
+
+fn parse_int(line: &[u8]) -> Result<i64> {
+    parse_integer(line).ok_or_else(|| Error::syntax("integer", line))
+}
+
+fn parse_length(line: &[u8]) -> Result<Option<usize>> {
+    let len = parse_int(line)?;
+    Ok(if len >= 0 { Some(len as usize) } else { None })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scan_frame_len() {
+        let value = b"*3\r\n$3\r\nGET\r\n*0\r\n*2\r\n:-1\r\n$-1\r\n";
+        assert_eq!(frame_len(value).unwrap(), Some(value.len()));
+        let pipeline = b"+OK\r\n*2\r\n:1\r\n$3\r\nfoo\r\n$5\r\nhel";
+        assert_eq!(frame_len(pipeline).unwrap(), Some(5));
+        assert_eq!(frame_len(&pipeline[5..]).unwrap(), Some(17));
+        assert_eq!(frame_len(&pipeline[22..]).unwrap(), None);
+        for end in 0..value.len() {
+            assert_eq!(frame_len(&value[..end]).unwrap(), None, "{}", end);
+        }
+        let nested = "*1\r\n".repeat(100_000) + "*-1\r\n";
+        assert_eq!(frame_len(nested.as_bytes()).unwrap(), Some(nested.len()));
+    }
+
+    #[test]
+    fn scan_frame_len_rejects_early() {
+        assert!(matches!(frame_len(b"?x"), Err(Error::Syntax { .. })));
+        assert!(frame_len(b"*1\r\n?").is_err());
+        let long = ":".to_string() + &" ".repeat(MAX_HEADER_LEN + 1);
+        let within_limit = &long.as_bytes()[..MAX_HEADER_LEN + 1];
+        assert!(frame_len(within_limit).unwrap().is_none());
+        assert!(frame_len(long.as_bytes()).is_err());
+        let long = "+".to_string() + &"x".repeat(MAX_HEADER_LEN);
+        assert!(frame_len(long.as_bytes()).unwrap().is_none());
+    }
+
+    #[test]
+    fn scan_check_depth() {
+        let value = b"*2\r\n*1\r\n*0\r\n*1\r\n:1\r\n";
+        assert!(check_depth(value, 3).is_ok());
+        assert!(matches!(check_depth(value, 2), Err(Error::DepthLimit(2))));
+        assert!(check_depth(b"*-1\r\n", 0).is_ok());
+        assert!(check_depth(b"*0\r\n", 0).is_err());
+        let nested = "*1\r\n".repeat(1000) + ":1\r\n";
+        assert!(check_depth(nested.as_bytes(), usize::MAX).is_ok());
+        assert!(check_depth(nested.as_bytes(), 999).is_err());
+    }
+
+    #[test]
+    fn scan_frame_len_invalid() {
+        for invalid in [
+            &b"?\r\n"[..],
+            b"*2\r\n:1\r\n!\r\n",
+            b":x\r\n",
+            b"$abc\r\n",
+            b"$3\r\nabcd\r\n",
+            b"*9223372036854775807\r\n*9223372036854775807\r\n*9223372036854775807\r\n",
+        ]
+        .iter()
+        {
+            assert!(frame_len(invalid).is_err(), "{:?}", invalid);
+        }
+    }
+}
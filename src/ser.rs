@@ -1,100 +1,230 @@
 use serde::{ser, Serialize};
 
 use crate::{Error, RESPType, Result};
-use serde::ser::SerializeSeq;
-use std::io::Write;
+use serde::ser::{Impossible, SerializeSeq};
+use std::io::{self, IoSlice, Write};
 use std::result;
 
 /// Serialize given value to string
 pub fn to_string(value: RESPType) -> Result<String> {
+    Ok(String::from_utf8(to_vec(&value)?)?)
+}
+
+/// Serialize given value to bytes
+pub fn to_vec<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: ?Sized + Serialize,
+{
     let mut buf: Vec<u8> = Vec::new();
-    let mut serializer = Serializer { writer: &mut buf };
-    value.serialize(&mut serializer)?;
-    Ok(String::from_utf8(buf)?)
+    to_writer(&mut buf, value)?;
+    Ok(buf)
+}
+
+/// Serialize given value into the writer
+pub fn to_writer<W, T>(writer: W, value: &T) -> Result<()>
+where
+    W: Write,
+    T: ?Sized + Serialize,
+{
+    let mut serializer = Serializer::new(writer);
+    value.serialize(&mut serializer)
+}
+
+/// Write a `RESPType` without recursion, so that arrays of any depth can be
+/// written with a bounded stack.
+pub fn write_value<W: Write>(mut writer: W, value: &RESPType) -> Result<()> {
+    let mut arrays = Vec::new();
+    let mut next = Some(value);
+    while let Some(value) = next {
+        match value {
+            RESPType::SimpleString(s) => write_simple(&mut writer, b'+', s)?,
+            RESPType::Error(s) => write_simple(&mut writer, b'-', s)?,
+            RESPType::Integer(int) => writer.write_all(Header::integer(b':', *int).as_bytes())?,
+            RESPType::BulkString(Some(bytes)) => write_frame(
+                &mut writer,
+                Header::length(b'$', bytes.len()).as_bytes(),
+                bytes,
+            )?,
+            RESPType::BulkString(None) => writer.write_all(b"$-1\r\n")?,
+            RESPType::Array(Some(items)) => {
+                writer.write_all(Header::length(b'*', items.len()).as_bytes())?;
+                arrays.push(items.iter());
+            }
+            RESPType::Array(None) => writer.write_all(b"*-1\r\n")?,
+        }
+        next = None;
+        while let Some(items) = arrays.last_mut() {
+            next = items.next();
+            if next.is_some() {
+                break;
+            }
+            arrays.pop();
+        }
+    }
+    Ok(())
+}
+
+/// Write a bulk string of `len` bytes copied from `reader`, without holding
+/// it in memory. Fails with `Error::Eof` if `reader` ends early, in which case
+/// the written frame is incomplete.
+pub fn write_bulk_stream<W: Write, R: io::Read>(mut writer: W, len: u64, reader: R) -> Result<()> {
+    writer.write_all(Header::new(b'$', false, len).as_bytes())?;
+    if io::copy(&mut reader.take(len), &mut writer)? < len {
+        return Err(Error::Eof);
+    }
+    writer.write_all(b"\r\n")?;
+    Ok(())
+}
+
+/// Newtype struct names used by `RESPType` to mark strings which should be
+/// written as simple strings and errors instead of bulk strings.
+pub(crate) const SIMPLE_STRING_TOKEN: &str = "$resp::SimpleString";
+pub(crate) const ERROR_TOKEN: &str = "$resp::Error";
+/// Unit struct name used by `RESPType` for null bulk strings
+pub(crate) const NULL_BULK_STRING_TOKEN: &str = "$resp::NullBulkString";
+
+/// How unit values (`()` and unit structs) are written. Each of them is read
+/// back as unit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnitRepr {
+    /// `+OK`
+    #[default]
+    Ok,
+    /// `$-1`
+    NullBulkString,
+    /// `*-1`
+    NullArray,
 }
 
 pub struct Serializer<W: Write> {
     writer: W,
+    simple_strings: bool,
+    resp3_booleans: bool,
+    unit: UnitRepr,
+    string_prefix: Option<u8>,
+}
+
+impl<W: Write> Serializer<W> {
+    pub fn new(writer: W) -> Self {
+        Serializer {
+            writer,
+            simple_strings: false,
+            resp3_booleans: false,
+            unit: UnitRepr::default(),
+            string_prefix: None,
+        }
+    }
+
+    /// Write strings without CR and LF as simple strings instead of bulk strings
+    pub fn simple_strings(mut self, enabled: bool) -> Self {
+        self.simple_strings = enabled;
+        self
+    }
+
+    /// Write booleans as RESP3 `#t` and `#f` instead of `:1` and `:0`
+    pub fn resp3_booleans(mut self, enabled: bool) -> Self {
+        self.resp3_booleans = enabled;
+        self
+    }
+
+    /// Write unit values as `repr`, `+OK` by default
+    pub fn unit(mut self, repr: UnitRepr) -> Self {
+        self.unit = repr;
+        self
+    }
 }
 
 impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
     type Ok = ();
     type Error = Error;
-    type SerializeSeq = Self;
-    type SerializeTuple = Self;
-    type SerializeTupleStruct = Self;
-    type SerializeTupleVariant = Self;
-    type SerializeMap = Self;
-    type SerializeStruct = Self;
-    type SerializeStructVariant = Self;
-
-    fn serialize_bool(self, _v: bool) -> Result<Self::Ok> {
-        unimplemented!()
+    type SerializeSeq = SerializeArray<'a, W>;
+    type SerializeTuple = SerializeArray<'a, W>;
+    type SerializeTupleStruct = SerializeArray<'a, W>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Impossible<(), Error>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    /// `:1` and `:0`, or `#t` and `#f` as set by `Serializer::resp3_booleans`
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok> {
+        self.writer.write_all(match (self.resp3_booleans, v) {
+            (false, false) => b":0\r\n",
+            (false, true) => b":1\r\n",
+            (true, false) => b"#f\r\n",
+            (true, true) => b"#t\r\n",
+        })?;
+        Ok(())
     }
 
-    fn serialize_i8(self, _v: i8) -> Result<Self::Ok> {
-        unimplemented!()
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok> {
+        self.serialize_i64(v as i64)
     }
 
-    fn serialize_i16(self, _v: i16) -> Result<Self::Ok> {
-        unimplemented!()
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok> {
+        self.serialize_i64(v as i64)
     }
 
-    fn serialize_i32(self, _v: i32) -> Result<Self::Ok> {
-        unimplemented!()
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok> {
+        self.serialize_i64(v as i64)
     }
 
     /// RESPType::Integer
     fn serialize_i64(self, v: i64) -> Result<Self::Ok> {
-        self.writer.write_all(b":")?;
-        self.writer.write_all(format!("{}", v).as_bytes())?;
-        self.writer.write_all(b"\r\n")?;
+        self.writer.write_all(Header::integer(b':', v).as_bytes())?;
         Ok(())
     }
 
-    fn serialize_u8(self, _v: u8) -> Result<Self::Ok> {
-        unimplemented!()
+    /// RESPType::Integer. Sequences of `u8` are bulk strings, see
+    /// `serialize_seq`.
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok> {
+        self.serialize_i64(v as i64)
     }
 
-    fn serialize_u16(self, _v: u16) -> Result<Self::Ok> {
-        unimplemented!()
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok> {
+        self.serialize_i64(v as i64)
     }
 
-    fn serialize_u32(self, _v: u32) -> Result<Self::Ok> {
-        unimplemented!()
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok> {
+        self.serialize_i64(v as i64)
     }
 
-    fn serialize_u64(self, _v: u64) -> Result<Self::Ok> {
-        unimplemented!()
+    /// RESPType::Integer, values above `i64::MAX` don't fit in it
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok> {
+        self.serialize_i64(u64_to_integer(v)?)
     }
 
     fn serialize_f32(self, _v: f32) -> Result<Self::Ok> {
-        unimplemented!()
+        Err(Error::unsupported("f32"))
     }
 
     fn serialize_f64(self, _v: f64) -> Result<Self::Ok> {
-        unimplemented!()
+        Err(Error::unsupported("f64"))
     }
 
     fn serialize_char(self, v: char) -> Result<Self::Ok> {
-        self.serialize_str(&v.to_string())
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
     }
 
-    /// RESPType::SimpleString and RESPType::Error
+    /// RESPType::BulkString, or RESPType::SimpleString with the
+    /// `simple_strings` option if it has no CR or LF
     fn serialize_str(self, v: &str) -> Result<Self::Ok> {
-        self.writer.write_all(v.as_bytes())?;
-        self.writer.write_all(b"\r\n")?;
-        Ok(())
+        if self.simple_strings && !v.contains(['\r', '\n']) {
+            self.string_prefix.get_or_insert(b'+');
+        }
+        self.serialize_bytes(v.as_bytes())
     }
 
-    /// RESPType::BulkString
+    /// RESPType::BulkString. Simple strings and errors of `RESPType` are
+    /// written through it as well.
     fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok> {
-        self.writer.write_all(b"$")?;
-        self.writer
-            .write_all(format!("{}\r\n", v.len() as u64).as_bytes())?;
-        self.writer.write_all(v)?;
-        self.writer.write_all(b"\r\n")?;
-        Ok(())
+        if let Some(prefix) = self.string_prefix.take() {
+            return write_simple(&mut self.writer, prefix, v);
+        }
+        write_frame(
+            &mut self.writer,
+            Header::length(b'$', v.len()).as_bytes(),
+            v,
+        )
     }
 
     /// RESPType::Array(None)
@@ -103,21 +233,31 @@ impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
         Ok(())
     }
 
-    fn serialize_some<T: ?Sized>(self, _value: &T) -> Result<Self::Ok>
+    /// The value itself
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        unimplemented!()
+        value.serialize(self)
     }
 
-    /// RESPType::BulkString(None)
+    /// Written as set by `Serializer::unit`
     fn serialize_unit(self) -> Result<Self::Ok> {
-        self.writer.write_all(b"$-1\r\n")?;
+        self.writer.write_all(match self.unit {
+            UnitRepr::Ok => b"+OK\r\n",
+            UnitRepr::NullBulkString => b"$-1\r\n",
+            UnitRepr::NullArray => b"*-1\r\n",
+        })?;
         Ok(())
     }
 
-    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok> {
-        unimplemented!()
+    /// Same as unit, or RESPType::BulkString(None) when named by the token
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok> {
+        if name == NULL_BULK_STRING_TOKEN {
+            self.writer.write_all(b"$-1\r\n")?;
+            return Ok(());
+        }
+        self.serialize_unit()
     }
 
     fn serialize_unit_variant(
@@ -126,21 +266,25 @@ impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
         _variant_index: u32,
         _variant: &'static str,
     ) -> Result<Self::Ok> {
-        unimplemented!()
+        Err(Error::unsupported("enum"))
     }
 
-    fn serialize_newtype_struct<T: ?Sized>(
-        self,
-        _name: &'static str,
-        _value: &T,
-    ) -> Result<Self::Ok>
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        unimplemented!()
+        // Other newtypes are transparent
+        self.string_prefix = match name {
+            SIMPLE_STRING_TOKEN => Some(b'+'),
+            ERROR_TOKEN => Some(b'-'),
+            _ => return value.serialize(self),
+        };
+        let result = value.serialize(&mut *self);
+        self.string_prefix = None;
+        result
     }
 
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T>(
         self,
         _name: &'static str,
         _variant_index: u32,
@@ -148,30 +292,35 @@ impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
         _value: &T,
     ) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        unimplemented!()
+        Err(Error::unsupported("enum"))
     }
 
-    /// RESPType::Array
+    /// RESPType::Array, or RESPType::BulkString for sequences of bytes. An
+    /// empty sequence is an empty array, see `Bytes`.
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
         match len {
-            Some(l) => self.writer.write_all(format!("*{}\r\n", l).as_bytes())?,
-            None => unimplemented!(),
-        };
-        Ok(self)
+            Some(len) => Ok(SerializeArray {
+                ser: self,
+                len,
+                state: ArrayState::Pending,
+            }),
+            None => Err(Error::unsupported("sequence of unknown length")),
+        }
     }
 
-    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
-        unimplemented!()
+    /// RESPType::Array of fixed length, written the same way as sequences
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
     }
 
     fn serialize_tuple_struct(
         self,
         _name: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
-        unimplemented!()
+        self.serialize_seq(Some(len))
     }
 
     fn serialize_tuple_variant(
@@ -181,15 +330,15 @@ impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
         _variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        unimplemented!()
+        Err(Error::unsupported("enum"))
     }
 
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
-        unimplemented!()
+        Err(Error::unsupported("map"))
     }
 
     fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
-        unimplemented!()
+        Err(Error::unsupported("struct"))
     }
 
     fn serialize_struct_variant(
@@ -199,135 +348,398 @@ impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
         _variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        unimplemented!()
+        Err(Error::unsupported("enum"))
     }
 }
 
-impl<'a, W: Write> ser::SerializeSeq for &'a mut Serializer<W> {
-    type Ok = ();
-    type Error = Error;
+/// Frames up to this size are assembled on the stack and written at once,
+/// larger ones are written with `write_vectored` without copying the payload.
+const BATCH_SIZE: usize = 256;
 
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<()>
-    where
-        T: Serialize,
-    {
-        value.serialize(&mut **self)?;
-        Ok(())
+/// Line with a type prefix and a decimal number (`:42\r\n`, `$5\r\n`),
+/// formatted on the stack.
+struct Header {
+    // prefix, sign, 20 digits of u64::MAX and CRLF
+    buf: [u8; 24],
+    start: usize,
+}
+
+impl Header {
+    fn integer(prefix: u8, value: i64) -> Self {
+        Header::new(prefix, value < 0, value.unsigned_abs())
     }
 
-    fn end(self) -> Result<Self::Ok> {
+    fn length(prefix: u8, len: usize) -> Self {
+        Header::new(prefix, false, len as u64)
+    }
+
+    fn new(prefix: u8, negative: bool, mut magnitude: u64) -> Self {
+        let mut buf = [0; 24];
+        let mut start = buf.len() - 2;
+        buf[start..].copy_from_slice(b"\r\n");
+        loop {
+            start -= 1;
+            buf[start] = b'0' + (magnitude % 10) as u8;
+            magnitude /= 10;
+            if magnitude == 0 {
+                break;
+            }
+        }
+        if negative {
+            start -= 1;
+            buf[start] = b'-';
+        }
+        start -= 1;
+        buf[start] = prefix;
+        Header { buf, start }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.buf[self.start..]
+    }
+}
+
+/// RESP integers are signed 64-bit
+pub(crate) fn u64_to_integer(v: u64) -> Result<i64> {
+    if v > i64::MAX as u64 {
+        return Err(Error::Message(format!("{} is too big for an integer", v)));
+    }
+    Ok(v as i64)
+}
+
+/// Write a simple string or an error
+fn write_simple<W: Write>(writer: &mut W, prefix: u8, v: &[u8]) -> Result<()> {
+    check_simple(v)?;
+    write_frame(writer, &[prefix], v)
+}
+
+/// CR or LF in a simple string or an error would end the line early and
+/// inject the rest as a new frame
+pub(crate) fn check_simple(v: &[u8]) -> Result<()> {
+    if v.iter().any(|&b| b == b'\r' || b == b'\n') {
+        return Err(Error::InvalidSimpleString);
+    }
+    Ok(())
+}
+
+/// Write `head`, `payload` and the closing CRLF.
+fn write_frame<W: Write>(writer: &mut W, head: &[u8], payload: &[u8]) -> Result<()> {
+    let len = head.len() + payload.len() + 2;
+    if len <= BATCH_SIZE {
+        let mut frame = [0; BATCH_SIZE];
+        frame[..head.len()].copy_from_slice(head);
+        frame[head.len()..len - 2].copy_from_slice(payload);
+        frame[len - 2..len].copy_from_slice(b"\r\n");
+        writer.write_all(&frame[..len])?;
+    } else {
+        let mut slices = [
+            IoSlice::new(head),
+            IoSlice::new(payload),
+            IoSlice::new(b"\r\n"),
+        ];
+        write_all_vectored(writer, &mut slices)?;
+    }
+    Ok(())
+}
+
+fn write_all_vectored<W: Write>(writer: &mut W, mut slices: &mut [IoSlice<'_>]) -> io::Result<()> {
+    while !slices.is_empty() {
+        match writer.write_vectored(slices) {
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+            Ok(n) => IoSlice::advance_slices(&mut slices, n),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// Header of the array is written on the first element, because sequences
+/// of `u8` (`Vec<u8>`, `&[u8]`) are collected and written as a bulk string.
+pub struct SerializeArray<'a, W: Write> {
+    ser: &'a mut Serializer<W>,
+    len: usize,
+    state: ArrayState,
+}
+
+enum ArrayState {
+    Pending,
+    Array,
+    Bytes(Vec<u8>),
+}
+
+impl<'a, W: Write> SerializeArray<'a, W> {
+    fn write_header(&mut self) -> Result<()> {
+        self.ser
+            .writer
+            .write_all(Header::length(b'*', self.len).as_bytes())?;
+        self.state = ArrayState::Array;
         Ok(())
     }
 }
 
-impl<'a, W: Write> ser::SerializeTupleStruct for &'a mut Serializer<W> {
+impl<'a, W: Write> ser::SerializeTuple for SerializeArray<'a, W> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, _value: &T) -> result::Result<(), Self::Error>
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        todo!()
+        SerializeSeq::serialize_element(self, value)
     }
 
-    fn end(self) -> result::Result<Self::Ok, Self::Error> {
-        todo!()
+    fn end(self) -> Result<Self::Ok> {
+        SerializeSeq::end(self)
     }
 }
 
-impl<'a, W: Write> ser::SerializeTupleVariant for &'a mut Serializer<W> {
+impl<'a, W: Write> ser::SerializeTupleStruct for SerializeArray<'a, W> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, _value: &T) -> result::Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        todo!()
+        SerializeSeq::serialize_element(self, value)
     }
 
-    fn end(self) -> result::Result<Self::Ok, Self::Error> {
-        todo!()
+    fn end(self) -> Result<Self::Ok> {
+        SerializeSeq::end(self)
     }
 }
 
-impl<'a, W: Write> ser::SerializeMap for &'a mut Serializer<W> {
+impl<'a, W: Write> ser::SerializeSeq for SerializeArray<'a, W> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_key<T: ?Sized>(&mut self, _key: &T) -> result::Result<(), Self::Error>
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        todo!()
+        match &mut self.state {
+            ArrayState::Pending => match value.serialize(ByteSerializer) {
+                Ok(byte) => {
+                    let mut bytes = Vec::with_capacity(self.len);
+                    bytes.push(byte);
+                    self.state = ArrayState::Bytes(bytes);
+                }
+                Err(_) => {
+                    self.write_header()?;
+                    value.serialize(&mut *self.ser)?;
+                }
+            },
+            ArrayState::Array => value.serialize(&mut *self.ser)?,
+            ArrayState::Bytes(bytes) => bytes.push(ByteSerializer::next_byte(value)?),
+        }
+        Ok(())
     }
 
-    fn serialize_value<T: ?Sized>(&mut self, _value: &T) -> result::Result<(), Self::Error>
+    fn end(mut self) -> Result<Self::Ok> {
+        match self.state {
+            ArrayState::Pending => self.write_header(),
+            ArrayState::Array => Ok(()),
+            ArrayState::Bytes(bytes) => ser::Serializer::serialize_bytes(self.ser, &bytes),
+        }
+    }
+}
+
+/// Accepts only `u8`, used to find out whether a sequence is a byte buffer
+pub(crate) struct ByteSerializer;
+
+impl ByteSerializer {
+    /// Element of a sequence which has started with a `u8`
+    pub(crate) fn next_byte<T>(value: &T) -> Result<u8>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        todo!()
+        value.serialize(ByteSerializer).map_err(|_| {
+            Error::Message("sequences starting with a u8 are bulk strings, expected u8".to_owned())
+        })
     }
 
-    fn end(self) -> result::Result<Self::Ok, Self::Error> {
-        todo!()
+    /// The first element of every sequence is probed, so the error carries no
+    /// message to keep that free of allocation
+    fn not_a_byte<T>(&self) -> Result<T> {
+        Err(Error::Message(String::new()))
     }
 }
 
-impl<'a, W: Write> ser::SerializeStruct for &'a mut Serializer<W> {
-    type Ok = ();
+impl ser::Serializer for ByteSerializer {
+    type Ok = u8;
     type Error = Error;
+    type SerializeSeq = Impossible<u8, Error>;
+    type SerializeTuple = Impossible<u8, Error>;
+    type SerializeTupleStruct = Impossible<u8, Error>;
+    type SerializeTupleVariant = Impossible<u8, Error>;
+    type SerializeMap = Impossible<u8, Error>;
+    type SerializeStruct = Impossible<u8, Error>;
+    type SerializeStructVariant = Impossible<u8, Error>;
+
+    fn serialize_bool(self, _v: bool) -> Result<u8> {
+        self.not_a_byte()
+    }
 
-    fn serialize_field<T: ?Sized>(
-        &mut self,
-        _key: &'static str,
-        _value: &T,
-    ) -> result::Result<(), Self::Error>
+    fn serialize_i8(self, _v: i8) -> Result<u8> {
+        self.not_a_byte()
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<u8> {
+        self.not_a_byte()
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<u8> {
+        self.not_a_byte()
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<u8> {
+        self.not_a_byte()
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<u8> {
+        Ok(v)
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<u8> {
+        self.not_a_byte()
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<u8> {
+        self.not_a_byte()
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<u8> {
+        self.not_a_byte()
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<u8> {
+        self.not_a_byte()
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<u8> {
+        self.not_a_byte()
+    }
+
+    fn serialize_char(self, _v: char) -> Result<u8> {
+        self.not_a_byte()
+    }
+
+    fn serialize_str(self, _v: &str) -> Result<u8> {
+        self.not_a_byte()
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<u8> {
+        self.not_a_byte()
+    }
+
+    fn serialize_none(self) -> Result<u8> {
+        self.not_a_byte()
+    }
+
+    fn serialize_some<T>(self, _value: &T) -> Result<u8>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        todo!()
+        self.not_a_byte()
     }
 
-    fn end(self) -> result::Result<Self::Ok, Self::Error> {
-        todo!()
+    fn serialize_unit(self) -> Result<u8> {
+        self.not_a_byte()
     }
-}
 
-impl<'a, W: Write> ser::SerializeStructVariant for &'a mut Serializer<W> {
-    type Ok = ();
-    type Error = Error;
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<u8> {
+        self.not_a_byte()
+    }
 
-    fn serialize_field<T: ?Sized>(
-        &mut self,
-        _key: &'static str,
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<u8> {
+        self.not_a_byte()
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, _value: &T) -> Result<u8>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.not_a_byte()
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
         _value: &T,
-    ) -> result::Result<(), Self::Error>
+    ) -> Result<u8>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        todo!()
+        self.not_a_byte()
     }
 
-    fn end(self) -> result::Result<Self::Ok, Self::Error> {
-        todo!()
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        self.not_a_byte()
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        self.not_a_byte()
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.not_a_byte()
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        self.not_a_byte()
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        self.not_a_byte()
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        self.not_a_byte()
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        self.not_a_byte()
     }
 }
 
-impl<'a, W: Write> ser::SerializeTuple for &'a mut Serializer<W> {
-    type Ok = ();
-    type Error = Error;
+/// Byte buffer which is always written as a bulk string, empty ones included.
+///
+/// Other sequences of `u8` are recognised by their first element, so an empty
+/// `Vec<u8>` or `&[u8]` is written as an empty array. `serde_bytes` has the
+/// same effect.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bytes<'a>(pub &'a [u8]);
 
-    fn serialize_element<T: ?Sized>(&mut self, _value: &T) -> Result<()>
+impl<'a> Serialize for Bytes<'a> {
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
     where
-        T: Serialize,
+        S: serde::Serializer,
     {
-        todo!()
-    }
-
-    fn end(self) -> Result<Self::Ok> {
-        todo!()
+        serializer.serialize_bytes(self.0)
     }
 }
 
@@ -340,12 +752,16 @@ impl serde::Serialize for RESPType {
         S: serde::Serializer,
     {
         match self {
-            RESPType::SimpleString(string) => serializer.serialize_str(&("+".to_owned() + string)),
-            RESPType::Error(string) => serializer.serialize_str(&("-".to_owned() + string)),
+            RESPType::SimpleString(string) => {
+                serializer.serialize_newtype_struct(SIMPLE_STRING_TOKEN, &Bytes(string))
+            }
+            RESPType::Error(string) => {
+                serializer.serialize_newtype_struct(ERROR_TOKEN, &Bytes(string))
+            }
             RESPType::Integer(int) => serializer.serialize_i64(*int),
             RESPType::BulkString(value) => match value {
                 Some(seq) => serializer.serialize_bytes(seq),
-                None => serializer.serialize_unit(),
+                None => serializer.serialize_unit_struct(NULL_BULK_STRING_TOKEN),
             },
             RESPType::Array(value) => match value {
                 Some(array) => {
@@ -362,18 +778,103 @@ impl serde::Serialize for RESPType {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    /// Counts allocations made by the current thread
+    struct Counting;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for Counting {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            ALLOCATIONS.with(|n| n.set(n.get() + 1));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: Counting = Counting;
+
+    /// Number of allocations made by `f`
+    pub(crate) fn allocations<F: FnOnce()>(f: F) -> usize {
+        let before = ALLOCATIONS.with(Cell::get);
+        f();
+        ALLOCATIONS.with(Cell::get) - before
+    }
+
+    /// Accepts at most `limit` bytes per call, to exercise partial writes
+    struct Chunked {
+        buf: Vec<u8>,
+        limit: usize,
+    }
+
+    impl Write for Chunked {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let n = buf.len().min(self.limit);
+            self.buf.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+            let mut limit = self.limit;
+            for buf in bufs {
+                let n = buf.len().min(limit);
+                self.buf.extend_from_slice(&buf[..n]);
+                limit -= n;
+            }
+            Ok(self.limit - limit)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn ser_header() {
+        assert_eq!(Header::integer(b':', 0).as_bytes(), b":0\r\n");
+        assert_eq!(
+            Header::integer(b':', i64::MIN).as_bytes(),
+            b":-9223372036854775808\r\n"
+        );
+        assert_eq!(
+            Header::length(b'$', usize::MAX).as_bytes(),
+            format!("${}\r\n", usize::MAX).as_bytes()
+        );
+    }
+
+    #[test]
+    fn ser_large_bulk_string() {
+        let payload = vec![b'x'; 1000];
+        let mut writer = Chunked {
+            buf: Vec::new(),
+            limit: 7,
+        };
+        to_writer(&mut writer, &RESPType::BulkString(Some(payload.clone()))).unwrap();
+        let mut expected = b"$1000\r\n".to_vec();
+        expected.extend_from_slice(&payload);
+        expected.extend_from_slice(b"\r\n");
+        assert_eq!(writer.buf, expected);
+    }
 
     #[test]
     fn ser_simple_string() {
-        let simple_str = RESPType::SimpleString("Hello".to_owned());
+        let simple_str = RESPType::SimpleString("Hello".into());
         assert_eq!("+Hello\r\n", to_string(simple_str).unwrap());
     }
 
     #[test]
     fn ser_error() {
-        let error = RESPType::Error("SERIALIZATION_ERROR".to_owned());
+        let error = RESPType::Error("SERIALIZATION_ERROR".into());
         assert_eq!("-SERIALIZATION_ERROR\r\n", to_string(error).unwrap());
     }
 
@@ -412,11 +913,215 @@ mod tests {
         let array = RESPType::Array(Some(vec![
             RESPType::BulkString(Some("Hello".to_owned().into())),
             RESPType::Array(Some(vec![RESPType::Integer(2)])),
-            RESPType::SimpleString("Hello".to_owned()),
+            RESPType::SimpleString("Hello".into()),
         ]));
         assert_eq!(
             "*3\r\n$5\r\nHello\r\n*1\r\n:2\r\n+Hello\r\n",
             to_string(array).unwrap()
         );
     }
+
+    #[test]
+    fn ser_str_as_bulk_string() {
+        assert_eq!(to_vec("Hello").unwrap(), b"$5\r\nHello\r\n");
+        assert_eq!(
+            to_vec(&vec!["a".to_owned(), "bc".to_owned()]).unwrap(),
+            b"*2\r\n$1\r\na\r\n$2\r\nbc\r\n"
+        );
+    }
+
+    #[test]
+    fn ser_bytes_as_bulk_string() {
+        assert_eq!(
+            to_vec(&b"\x00\xff".to_vec()).unwrap(),
+            b"$2\r\n\x00\xff\r\n"
+        );
+        assert_eq!(to_vec(&b"ab"[..]).unwrap(), b"$2\r\nab\r\n");
+        assert_eq!(to_vec(&Bytes(&[])).unwrap(), b"$0\r\n\r\n");
+        // Nothing tells an empty Vec<u8> from any other empty sequence
+        assert_eq!(
+            to_vec(&("SET", "k", Vec::<u8>::new())).unwrap(),
+            b"*3\r\n$3\r\nSET\r\n$1\r\nk\r\n*0\r\n"
+        );
+        assert_eq!(
+            to_vec(&("SET", "k", Bytes(&[]))).unwrap(),
+            b"*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$0\r\n\r\n"
+        );
+        let err = to_vec(&(1u8, "a")).unwrap_err();
+        assert!(err.to_string().contains("bulk strings"), "{}", err);
+        assert_eq!(to_vec(&("a", 1u8)).unwrap(), b"*2\r\n$1\r\na\r\n:1\r\n");
+        assert_eq!(
+            to_vec(&vec![("x", 1u8)]).unwrap(),
+            b"*1\r\n*2\r\n$1\r\nx\r\n:1\r\n"
+        );
+    }
+
+    #[test]
+    fn ser_seq_of_unknown_length() {
+        struct Evens;
+
+        impl Serialize for Evens {
+            fn serialize<S: ser::Serializer>(
+                &self,
+                serializer: S,
+            ) -> result::Result<S::Ok, S::Error> {
+                serializer.collect_seq((0..4i64).filter(|n| n % 2 == 0))
+            }
+        }
+
+        let err = to_vec(&Evens).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "sequence of unknown length values are not supported"
+        );
+        assert_eq!(crate::to_value(&Evens).unwrap_err(), err);
+    }
+
+    #[test]
+    fn ser_str_as_simple_string() {
+        let mut buf = Vec::new();
+        let mut serializer = Serializer::new(&mut buf).simple_strings(true);
+        "OK".serialize(&mut serializer).unwrap();
+        "multi\r\nline".serialize(&mut serializer).unwrap();
+        assert_eq!(buf, b"+OK\r\n$11\r\nmulti\r\nline\r\n");
+    }
+
+    #[test]
+    fn ser_simple_string_with_crlf() {
+        let simple_str = RESPType::SimpleString("OK\r\n+INJECTED".into());
+        assert!(matches!(
+            to_string(simple_str),
+            Err(Error::InvalidSimpleString)
+        ));
+        let error = RESPType::Error("ERR\nINJECTED".into());
+        assert!(matches!(to_string(error), Err(Error::InvalidSimpleString)));
+    }
+
+    #[test]
+    fn ser_binary_simple_string() {
+        let simple_str = RESPType::SimpleString(b"\xe9t\xe9".to_vec());
+        assert_eq!(to_vec(&simple_str).unwrap(), b"+\xe9t\xe9\r\n");
+    }
+
+    #[test]
+    fn ser_write_value() {
+        let depth = 10_000;
+        let mut value = RESPType::Array(Some(vec![
+            RESPType::SimpleString(b"OK".to_vec()),
+            RESPType::BulkString(None),
+        ]));
+        for _ in 0..depth {
+            value = RESPType::Array(Some(vec![value]));
+        }
+        let mut buf = Vec::new();
+        write_value(&mut buf, &value).unwrap();
+        let expected = "*1\r\n".repeat(depth) + "*2\r\n+OK\r\n$-1\r\n";
+        assert_eq!(buf, expected.as_bytes());
+        // Take the value apart without recursion
+        while let RESPType::Array(Some(mut items)) = value {
+            value = items.pop().unwrap();
+        }
+
+        let invalid = RESPType::Array(Some(vec![RESPType::Error(b"a\nb".to_vec())]));
+        assert!(matches!(
+            write_value(Vec::new(), &invalid),
+            Err(Error::InvalidSimpleString)
+        ));
+    }
+
+    #[test]
+    fn ser_write_bulk_stream() {
+        let mut buf = Vec::new();
+        write_bulk_stream(&mut buf, 5, &b"hello world"[..]).unwrap();
+        assert_eq!(buf, b"$5\r\nhello\r\n");
+        let mut buf = Vec::new();
+        write_bulk_stream(&mut buf, 0, io::empty()).unwrap();
+        assert_eq!(buf, b"$0\r\n\r\n");
+        assert!(matches!(
+            write_bulk_stream(Vec::new(), 10, &b"short"[..]),
+            Err(Error::Eof)
+        ));
+    }
+
+    #[test]
+    fn ser_tuple() {
+        #[derive(serde::Serialize)]
+        struct Pair(&'static str, i64);
+
+        assert_eq!(
+            to_vec(&("key", 5i64)).unwrap(),
+            b"*2\r\n$3\r\nkey\r\n:5\r\n"
+        );
+        assert_eq!(to_vec(&Pair("a", 1)).unwrap(), b"*2\r\n$1\r\na\r\n:1\r\n");
+        assert_eq!(to_vec(b"abc").unwrap(), b"$3\r\nabc\r\n");
+    }
+
+    #[test]
+    fn ser_newtype_and_unit() {
+        #[derive(serde::Serialize)]
+        struct UserId(u64);
+        #[derive(serde::Serialize)]
+        struct Ack;
+
+        assert_eq!(to_vec(&UserId(42)).unwrap(), b":42\r\n");
+        assert_eq!(
+            to_vec(&UserId(i64::MAX as u64)).unwrap(),
+            b":9223372036854775807\r\n"
+        );
+        let err = to_vec(&UserId(i64::MAX as u64 + 1)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "9223372036854775808 is too big for an integer"
+        );
+        assert_eq!(
+            to_vec(&(-1i8, 2u16, 3u32)).unwrap(),
+            b"*3\r\n:-1\r\n:2\r\n:3\r\n"
+        );
+        assert_eq!(to_vec(&()).unwrap(), b"+OK\r\n");
+        assert_eq!(to_vec(&Ack).unwrap(), b"+OK\r\n");
+        for (repr, expected) in [
+            (UnitRepr::Ok, &b"+OK\r\n"[..]),
+            (UnitRepr::NullBulkString, b"$-1\r\n"),
+            (UnitRepr::NullArray, b"*-1\r\n"),
+        ] {
+            let mut buf = Vec::new();
+            Ack.serialize(&mut Serializer::new(&mut buf).unit(repr))
+                .unwrap();
+            assert_eq!(buf, expected);
+            let mut reader = &buf[..];
+            crate::from_buf_reader::<_, ()>(&mut reader).unwrap();
+        }
+        assert_eq!(to_vec(&RESPType::BulkString(None)).unwrap(), b"$-1\r\n");
+    }
+
+    #[test]
+    fn ser_bool_and_char() {
+        assert_eq!(to_vec(&(true, false)).unwrap(), b"*2\r\n:1\r\n:0\r\n");
+        let mut buf = Vec::new();
+        (true, false)
+            .serialize(&mut Serializer::new(&mut buf).resp3_booleans(true))
+            .unwrap();
+        assert_eq!(buf, b"*2\r\n#t\r\n#f\r\n");
+        assert_eq!(
+            to_vec(&'\u{20ac}').unwrap(),
+            "$3\r\n\u{20ac}\r\n".as_bytes()
+        );
+    }
+
+    #[test]
+    fn ser_no_allocation() {
+        let command = RESPType::Array(Some(vec![
+            RESPType::BulkString(Some(b"GET".to_vec())),
+            RESPType::BulkString(Some(b"k".to_vec())),
+        ]));
+        let nested = vec![vec!["a"]; 3];
+        let mut buf = Vec::with_capacity(1024);
+        let count = allocations(|| {
+            to_writer(&mut buf, &command).unwrap();
+            to_writer(&mut buf, &("SET", "k", 5i64)).unwrap();
+            to_writer(&mut buf, &nested).unwrap();
+        });
+        assert_eq!(count, 0);
+        assert!(buf.starts_with(b"*2\r\n$3\r\nGET\r\n$1\r\nk\r\n*3\r\n"));
+    }
 }
@@ -0,0 +1,975 @@
+use serde::de::value::{SeqAccessDeserializer, U32Deserializer};
+use serde::de::{
+    self, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, Unexpected,
+    VariantAccess, Visitor,
+};
+use serde::ser::{self, Impossible, Serialize};
+
+use crate::de::{
+    check_map_len, check_ok, check_tuple_len, integer_to_bool, visit_bytes_as_seq, visit_char,
+    visit_error, visit_number, visit_option, visit_simple_str, visit_simple_string,
+    ByteBufDeserializer, ARRAY, BULK_STRING, ERROR, INTEGER, NULL_ARRAY, NULL_BULK_STRING,
+    RESP_TYPE_TOKEN, SIMPLE_STRING,
+};
+use crate::ser::{
+    check_simple, u64_to_integer, ByteSerializer, UnitRepr, ERROR_TOKEN, NULL_BULK_STRING_TOKEN,
+    SIMPLE_STRING_TOKEN,
+};
+use crate::{Error, RESPType, Result};
+use std::vec;
+
+/// Convert given value to `RESPType` without encoding it to bytes.
+///
+/// The mapping is the same as for `ser::Serializer`, so encoding the result
+/// with `ser::to_vec` gives exactly the same bytes as encoding `value` directly.
+pub fn to_value<T>(value: &T) -> Result<RESPType>
+where
+    T: ?Sized + Serialize,
+{
+    value.serialize(Serializer::new())
+}
+
+/// Serializer whose output is an in-memory `RESPType`
+#[derive(Clone, Copy, Default)]
+pub struct Serializer {
+    simple_strings: bool,
+    unit: UnitRepr,
+    string_prefix: Option<u8>,
+}
+
+impl Serializer {
+    pub fn new() -> Self {
+        Serializer::default()
+    }
+
+    /// Same as `ser::Serializer::simple_strings`
+    pub fn simple_strings(mut self, enabled: bool) -> Self {
+        self.simple_strings = enabled;
+        self
+    }
+
+    /// Same as `ser::Serializer::unit`
+    pub fn unit(mut self, repr: UnitRepr) -> Self {
+        self.unit = repr;
+        self
+    }
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = RESPType;
+    type Error = Error;
+    type SerializeSeq = SerializeArray;
+    type SerializeTuple = SerializeArray;
+    type SerializeTupleStruct = SerializeArray;
+    type SerializeTupleVariant = Impossible<RESPType, Error>;
+    type SerializeMap = Impossible<RESPType, Error>;
+    type SerializeStruct = Impossible<RESPType, Error>;
+    type SerializeStructVariant = Impossible<RESPType, Error>;
+
+    /// RESPType::Integer, there is no boolean type in RESP2
+    fn serialize_bool(self, v: bool) -> Result<RESPType> {
+        Ok(RESPType::Integer(v as i64))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<RESPType> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<RESPType> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<RESPType> {
+        self.serialize_i64(v as i64)
+    }
+
+    /// RESPType::Integer
+    fn serialize_i64(self, v: i64) -> Result<RESPType> {
+        Ok(RESPType::Integer(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<RESPType> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<RESPType> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<RESPType> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<RESPType> {
+        self.serialize_i64(u64_to_integer(v)?)
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<RESPType> {
+        Err(Error::unsupported("f32"))
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<RESPType> {
+        Err(Error::unsupported("f64"))
+    }
+
+    fn serialize_char(self, v: char) -> Result<RESPType> {
+        self.serialize_str(&v.to_string())
+    }
+
+    /// RESPType::BulkString, or RESPType::SimpleString with the
+    /// `simple_strings` option if it has no CR or LF
+    fn serialize_str(mut self, v: &str) -> Result<RESPType> {
+        if self.simple_strings && !v.contains(['\r', '\n']) {
+            self.string_prefix.get_or_insert(b'+');
+        }
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    /// RESPType::BulkString. Simple strings and errors of `RESPType` are
+    /// written through it as well.
+    fn serialize_bytes(self, v: &[u8]) -> Result<RESPType> {
+        if self.string_prefix.is_some() {
+            check_simple(v)?;
+        }
+        match self.string_prefix {
+            Some(b'-') => Ok(RESPType::Error(v.to_vec())),
+            Some(_) => Ok(RESPType::SimpleString(v.to_vec())),
+            None => Ok(RESPType::BulkString(Some(v.to_vec()))),
+        }
+    }
+
+    /// RESPType::Array(None)
+    fn serialize_none(self) -> Result<RESPType> {
+        Ok(RESPType::Array(None))
+    }
+
+    /// The value itself
+    fn serialize_some<T>(self, value: &T) -> Result<RESPType>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<RESPType> {
+        Ok(match self.unit {
+            UnitRepr::Ok => RESPType::SimpleString(b"OK".to_vec()),
+            UnitRepr::NullBulkString => RESPType::BulkString(None),
+            UnitRepr::NullArray => RESPType::Array(None),
+        })
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<RESPType> {
+        if name == NULL_BULK_STRING_TOKEN {
+            return Ok(RESPType::BulkString(None));
+        }
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<RESPType> {
+        Err(Error::unsupported("enum"))
+    }
+
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<RESPType>
+    where
+        T: ?Sized + Serialize,
+    {
+        let string_prefix = match name {
+            SIMPLE_STRING_TOKEN => Some(b'+'),
+            ERROR_TOKEN => Some(b'-'),
+            _ => return value.serialize(self),
+        };
+        value.serialize(Serializer {
+            string_prefix,
+            ..self
+        })
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<RESPType>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(Error::unsupported("enum"))
+    }
+
+    /// RESPType::Array, or RESPType::BulkString for sequences of bytes. An
+    /// empty sequence is an empty array, see `ser::Bytes`.
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+        match len {
+            Some(l) => Ok(SerializeArray {
+                ser: self,
+                array: Vec::with_capacity(l),
+                bytes: None,
+            }),
+            None => Err(Error::unsupported("sequence of unknown length")),
+        }
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(Error::unsupported("enum"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(Error::unsupported("map"))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(Error::unsupported("struct"))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(Error::unsupported("enum"))
+    }
+}
+
+pub struct SerializeArray {
+    ser: Serializer,
+    array: Vec<RESPType>,
+    bytes: Option<Vec<u8>>,
+}
+
+impl ser::SerializeSeq for SerializeArray {
+    type Ok = RESPType;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        if let Some(bytes) = self.bytes.as_mut() {
+            bytes.push(ByteSerializer::next_byte(value)?);
+        } else if self.array.is_empty() {
+            match value.serialize(ByteSerializer) {
+                Ok(byte) => self.bytes = Some(vec![byte]),
+                Err(_) => self.array.push(value.serialize(self.ser)?),
+            }
+        } else {
+            self.array.push(value.serialize(self.ser)?);
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<RESPType> {
+        match self.bytes {
+            Some(bytes) => Ok(RESPType::BulkString(Some(bytes))),
+            None => Ok(RESPType::Array(Some(self.array))),
+        }
+    }
+}
+
+impl ser::SerializeTuple for SerializeArray {
+    type Ok = RESPType;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<RESPType> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeArray {
+    type Ok = RESPType;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<RESPType> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+/// Payloads are moved out of an owned `RESPType` and cloned out of a borrowed
+/// one
+trait IntoOwned<T> {
+    fn into_owned(self) -> T;
+}
+
+impl<T> IntoOwned<T> for T {
+    fn into_owned(self) -> T {
+        self
+    }
+}
+
+impl<T: Clone> IntoOwned<T> for &T {
+    fn into_owned(self) -> T {
+        self.clone()
+    }
+}
+
+// `serde::forward_to_deserialize_any` refers to `Self::Error`, which is
+// ambiguous for `RESPType` because of the `RESPType::Error` variant.
+macro_rules! forward_to_deserialize_any {
+    () => {
+        forward_to_deserialize_any! {
+            deserialize_i128() deserialize_u128() deserialize_str() deserialize_string()
+            deserialize_bytes() deserialize_byte_buf() deserialize_identifier()
+            deserialize_ignored_any()
+        }
+    };
+    ($($method:ident($($arg:ident: $ty:ty),*))*) => {
+        $(
+            fn $method<V>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                self.deserialize_any(visitor)
+            }
+        )*
+    };
+}
+
+/// Numbers are integers, or text in simple and bulk strings, as for
+/// `de::Deserializer`
+macro_rules! deserialize_number {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                match self {
+                    RESPType::SimpleString(s) | RESPType::BulkString(Some(s)) => {
+                        visit_number(&s, visitor)
+                    }
+                    value => value.deserialize_any(visitor),
+                }
+            }
+        )*
+    };
+}
+
+/// Implement `de::Deserializer` for `RESPType` and `&'de RESPType`, together
+/// with the `ValueFrame` which reads the exact type of the value
+macro_rules! impl_deserializer {
+    ($ty:ty, $visit_simple_string:ident) => {
+        /// `RESPType` can be deserialized into any `T: Deserialize` using the
+        /// same mapping as `de::Deserializer` uses for encoded values. Values
+        /// which the mapping hands over by value (errors and bulk strings) are
+        /// cloned out of a borrowed `RESPType`.
+        impl<'de> de::Deserializer<'de> for $ty {
+            type Error = Error;
+
+            fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                match self {
+                    RESPType::SimpleString(string) => $visit_simple_string(string, visitor),
+                    RESPType::Error(string) => visit_error(string.into_owned(), visitor),
+                    RESPType::Integer(int) => visitor.visit_i64(int.into_owned()),
+                    RESPType::BulkString(Some(bytes)) => visitor.visit_byte_buf(bytes.into_owned()),
+                    RESPType::BulkString(None) => visitor.visit_none(),
+                    RESPType::Array(Some(array)) => visitor.visit_seq(ArrayDeserializer {
+                        iter: array.into_iter(),
+                    }),
+                    RESPType::Array(None) => visitor.visit_unit(),
+                }
+            }
+
+            fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                match self {
+                    RESPType::BulkString(Some(bytes)) => {
+                        visit_bytes_as_seq(bytes.into_owned(), visitor)
+                    }
+                    value => value.deserialize_any(visitor),
+                }
+            }
+
+            /// Arrays and bulk strings of other length fail, other types are
+            /// left to the visitor
+            fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                match &self {
+                    RESPType::Array(Some(items)) => check_tuple_len(len, items.len(), false)?,
+                    RESPType::BulkString(Some(bytes)) => check_tuple_len(len, bytes.len(), true)?,
+                    _ => {}
+                }
+                self.deserialize_seq(visitor)
+            }
+
+            fn deserialize_tuple_struct<V>(
+                self,
+                _name: &'static str,
+                len: usize,
+                visitor: V,
+            ) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                self.deserialize_tuple(len, visitor)
+            }
+
+            fn deserialize_newtype_struct<V>(
+                self,
+                name: &'static str,
+                visitor: V,
+            ) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                match name {
+                    RESP_TYPE_TOKEN => visitor.visit_enum(ValueFrame(self)),
+                    _ => visitor.visit_newtype_struct(self),
+                }
+            }
+
+            fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                match self {
+                    RESPType::Integer(int) => visitor.visit_bool(integer_to_bool(int.into_owned())?),
+                    value => value.deserialize_any(visitor),
+                }
+            }
+
+            fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                match self {
+                    RESPType::SimpleString(s) | RESPType::BulkString(Some(s)) => {
+                        visit_char(&s, visitor)
+                    }
+                    value => value.deserialize_any(visitor),
+                }
+            }
+
+            /// `+OK` is unit, and so are both nulls
+            fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                match self {
+                    RESPType::SimpleString(line) => {
+                        check_ok(&line)?;
+                        visitor.visit_unit()
+                    }
+                    RESPType::BulkString(None) | RESPType::Array(None) => visitor.visit_unit(),
+                    value => value.deserialize_any(visitor),
+                }
+            }
+
+            fn deserialize_unit_struct<V>(
+                self,
+                _name: &'static str,
+                visitor: V,
+            ) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                self.deserialize_unit(visitor)
+            }
+
+            fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                match self {
+                    RESPType::Array(Some(items)) => {
+                        check_map_len(items.len())?;
+                        visitor.visit_map(ArrayDeserializer {
+                            iter: items.into_iter(),
+                        })
+                    }
+                    value => value.deserialize_any(visitor),
+                }
+            }
+
+            fn deserialize_struct<V>(
+                self,
+                _name: &'static str,
+                _fields: &'static [&'static str],
+                visitor: V,
+            ) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                self.deserialize_map(visitor)
+            }
+
+            fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                let null = matches!(self, RESPType::BulkString(None) | RESPType::Array(None));
+                visit_option(Some(self).filter(|_| !null), visitor)
+            }
+
+            fn deserialize_enum<V>(
+                self,
+                _name: &'static str,
+                _variants: &'static [&'static str],
+                _visitor: V,
+            ) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                Err(Error::unsupported("enum"))
+            }
+
+            deserialize_number!(
+                deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_u8
+                deserialize_u16 deserialize_u32 deserialize_u64 deserialize_f32 deserialize_f64
+            );
+            forward_to_deserialize_any!();
+        }
+
+        impl<'de> IntoDeserializer<'de, Error> for $ty {
+            type Deserializer = Self;
+
+            fn into_deserializer(self) -> Self::Deserializer {
+                self
+            }
+        }
+
+        impl<'de> EnumAccess<'de> for ValueFrame<$ty> {
+            type Error = Error;
+            type Variant = Self;
+
+            fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self)>
+            where
+                V: DeserializeSeed<'de>,
+            {
+                let index: U32Deserializer<Error> = variant_index(&self.0).into_deserializer();
+                Ok((seed.deserialize(index)?, self))
+            }
+        }
+
+        impl<'de> VariantAccess<'de> for ValueFrame<$ty> {
+            type Error = Error;
+
+            fn unit_variant(self) -> Result<()> {
+                Ok(())
+            }
+
+            fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+            where
+                T: DeserializeSeed<'de>,
+            {
+                match self.0 {
+                    RESPType::SimpleString(bytes)
+                    | RESPType::Error(bytes)
+                    | RESPType::BulkString(Some(bytes)) => {
+                        seed.deserialize(ByteBufDeserializer(bytes.into_owned()))
+                    }
+                    RESPType::Integer(int) => {
+                        let int: i64 = int.into_owned();
+                        seed.deserialize(int.into_deserializer())
+                    }
+                    RESPType::Array(Some(array)) => {
+                        seed.deserialize(SeqAccessDeserializer::new(ArrayDeserializer {
+                            iter: array.into_iter(),
+                        }))
+                    }
+                    RESPType::BulkString(None) | RESPType::Array(None) => Err(
+                        de::Error::invalid_type(Unexpected::Unit, &"newtype variant"),
+                    ),
+                }
+            }
+
+            fn tuple_variant<V>(self, _len: usize, _visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                Err(de::Error::invalid_type(
+                    Unexpected::NewtypeVariant,
+                    &"tuple variant",
+                ))
+            }
+
+            fn struct_variant<V>(
+                self,
+                _fields: &'static [&'static str],
+                _visitor: V,
+            ) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                Err(de::Error::invalid_type(
+                    Unexpected::NewtypeVariant,
+                    &"struct variant",
+                ))
+            }
+        }
+    };
+}
+
+impl_deserializer!(RESPType, visit_simple_string);
+impl_deserializer!(&'de RESPType, visit_simple_str);
+
+/// Elements of an owned or a borrowed array
+struct ArrayDeserializer<I> {
+    iter: I,
+}
+
+impl<'de, I> SeqAccess<'de> for ArrayDeserializer<I>
+where
+    I: ExactSizeIterator,
+    I::Item: de::Deserializer<'de, Error = Error>,
+{
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some(value) => seed.deserialize(value).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+/// Keys and values of a map alternate in the array
+impl<'de, I> MapAccess<'de> for ArrayDeserializer<I>
+where
+    I: ExactSizeIterator,
+    I::Item: de::Deserializer<'de, Error = Error>,
+{
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        self.next_element_seed(seed)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        match self.next_element_seed(seed)? {
+            Some(value) => Ok(value),
+            None => Err(de::Error::custom("value is missing")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len() / 2)
+    }
+}
+
+/// Exact type of a value for `RESPType`, see `de::RESP_TYPE_TOKEN`
+struct ValueFrame<T>(T);
+
+fn variant_index(value: &RESPType) -> u32 {
+    match value {
+        RESPType::SimpleString(_) => SIMPLE_STRING,
+        RESPType::Error(_) => ERROR,
+        RESPType::Integer(_) => INTEGER,
+        RESPType::BulkString(Some(_)) => BULK_STRING,
+        RESPType::BulkString(None) => NULL_BULK_STRING,
+        RESPType::Array(Some(_)) => ARRAY,
+        RESPType::Array(None) => NULL_ARRAY,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::de::from_string;
+    use crate::ser::{to_vec, Bytes};
+    use crate::Pairs;
+    use serde::Deserialize;
+
+    #[test]
+    fn value_roundtrip() {
+        let array = RESPType::Array(Some(vec![
+            RESPType::BulkString(Some("Hello".to_owned().into())),
+            RESPType::Array(Some(vec![RESPType::Integer(2), RESPType::Array(None)])),
+            RESPType::SimpleString("Hello".into()),
+            RESPType::Error("ERR".into()),
+            RESPType::Error(b"ERR \xff".to_vec()),
+            RESPType::BulkString(None),
+        ]));
+        assert_eq!(to_value(&array).unwrap(), array);
+        assert_eq!(RESPType::deserialize(&array).unwrap(), array);
+    }
+
+    #[test]
+    fn value_matches_bytes() {
+        let value = vec![vec![1i64, 2], vec![], vec![3]];
+        assert_eq!(
+            to_vec(&to_value(&value).unwrap()).unwrap(),
+            to_vec(&value).unwrap()
+        );
+    }
+
+    #[test]
+    fn value_strings_and_bytes() {
+        assert_eq!(
+            to_value("Hello").unwrap(),
+            RESPType::BulkString(Some(b"Hello".to_vec()))
+        );
+        assert_eq!(
+            to_value(&b"Hello".to_vec()).unwrap(),
+            RESPType::BulkString(Some(b"Hello".to_vec()))
+        );
+        assert_eq!(
+            to_value(&Bytes(&[])).unwrap(),
+            RESPType::BulkString(Some(Vec::new()))
+        );
+        assert_eq!(
+            to_value(&Vec::<u8>::new()).unwrap(),
+            RESPType::Array(Some(Vec::new()))
+        );
+        let serializer = Serializer::new().simple_strings(true);
+        assert_eq!(
+            "Hello".serialize(serializer).unwrap(),
+            RESPType::SimpleString("Hello".into())
+        );
+        assert_eq!(
+            "Hello\r\n".serialize(serializer).unwrap(),
+            RESPType::BulkString(Some(b"Hello\r\n".to_vec()))
+        );
+    }
+
+    #[test]
+    fn value_into_resp_type() {
+        let s = "*4\r\n:23\r\n-ERR\r\n+OK\r\n*2\r\n$-1\r\n*-1\r\n";
+        let value: RESPType = from_string(s.to_owned()).unwrap();
+        let from_ref = RESPType::deserialize(&value).unwrap();
+        assert_eq!(from_ref, value);
+        assert_eq!(RESPType::deserialize(value).unwrap(), from_ref);
+    }
+
+    #[test]
+    fn value_into_typed() {
+        let s = "*2\r\n$5\r\nHello\r\n+there\r\n";
+        let value: RESPType = from_string(s.to_owned()).unwrap();
+        let typed: Vec<String> = from_string(s.to_owned()).unwrap();
+        assert_eq!(Vec::<String>::deserialize(&value).unwrap(), typed);
+        assert_eq!(Vec::<String>::deserialize(value).unwrap(), typed);
+        assert_eq!(i64::deserialize(RESPType::Integer(5)).unwrap(), 5);
+        let bytes = RESPType::BulkString(Some(b"Hello".to_vec()));
+        assert_eq!(Vec::<u8>::deserialize(&bytes).unwrap(), b"Hello");
+    }
+
+    #[test]
+    fn value_tuple() {
+        let value = to_value(&("key", 5i64)).unwrap();
+        assert_eq!(
+            value,
+            RESPType::Array(Some(vec![
+                RESPType::BulkString(Some(b"key".to_vec())),
+                RESPType::Integer(5)
+            ]))
+        );
+        let tuple = <(String, i64)>::deserialize(&value).unwrap();
+        assert_eq!(tuple, ("key".to_owned(), 5));
+        assert!(matches!(
+            <(String, i64, i64)>::deserialize(value),
+            Err(Error::LengthMismatch {
+                expected: 3,
+                found: 2,
+                bulk_string: false
+            })
+        ));
+        let value = RESPType::BulkString(Some(b"abc".to_vec()));
+        assert_eq!(
+            <[u8; 2]>::deserialize(value).unwrap_err().to_string(),
+            "expected a bulk string of 2 bytes, found 3"
+        );
+    }
+
+    #[test]
+    fn value_option() {
+        let pair = (Some("x"), None::<String>);
+        let bytes = to_vec(&pair).unwrap();
+        assert_eq!(bytes, b"*2\r\n$1\r\nx\r\n*-1\r\n");
+        let decoded: (Option<String>, Option<String>) =
+            from_string(String::from_utf8(bytes).unwrap()).unwrap();
+        assert_eq!(decoded, (Some("x".to_owned()), None));
+        let value = to_value(&pair).unwrap();
+        assert_eq!(
+            <(Option<String>, Option<String>)>::deserialize(value).unwrap(),
+            decoded
+        );
+    }
+
+    #[test]
+    fn value_newtype_and_unit() {
+        #[derive(Debug, Deserialize, PartialEq, serde::Serialize)]
+        struct UserId(u64);
+        #[derive(Debug, Deserialize, PartialEq, serde::Serialize)]
+        struct Ack;
+
+        let value = to_value(&UserId(7)).unwrap();
+        assert_eq!(value, RESPType::Integer(7));
+        assert_eq!(UserId::deserialize(&value).unwrap(), UserId(7));
+        assert_eq!(
+            to_value(&UserId(u64::MAX)).unwrap_err(),
+            to_vec(&UserId(u64::MAX)).unwrap_err()
+        );
+        assert!(UserId::deserialize(RESPType::Integer(-1)).is_err());
+        let ok = to_value(&Ack).unwrap();
+        assert_eq!(ok, RESPType::SimpleString(b"OK".to_vec()));
+        assert_eq!(Ack::deserialize(ok).unwrap(), Ack);
+        let null = Ack
+            .serialize(Serializer::default().unit(UnitRepr::NullArray))
+            .unwrap();
+        assert_eq!(null, RESPType::Array(None));
+        for repr in [UnitRepr::Ok, UnitRepr::NullBulkString, UnitRepr::NullArray] {
+            let value = ().serialize(Serializer::default().unit(repr)).unwrap();
+            <()>::deserialize(&value).unwrap();
+            assert_eq!(Ack::deserialize(value).unwrap(), Ack);
+        }
+        assert!(Ack::deserialize(RESPType::SimpleString(b"PONG".to_vec())).is_err());
+    }
+
+    #[test]
+    fn value_bool_and_char() {
+        let value = to_value(&true).unwrap();
+        assert_eq!(value, RESPType::Integer(1));
+        assert!(bool::deserialize(&value).unwrap());
+        assert!(bool::deserialize(RESPType::Integer(2)).is_err());
+        let value = to_value(&'x').unwrap();
+        assert_eq!(char::deserialize(&value).unwrap(), 'x');
+        assert_eq!(
+            char::deserialize(RESPType::SimpleString(b"y".to_vec())).unwrap(),
+            'y'
+        );
+        assert!(char::deserialize(RESPType::BulkString(Some(b"xy".to_vec()))).is_err());
+    }
+
+    #[test]
+    fn value_struct_from_pairs() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Info {
+            length: i64,
+            name: String,
+        }
+
+        let input = "*6\r\n$6\r\nlength\r\n:5\r\n$6\r\ngroups\r\n*1\r\n:1\r\n\
+                     +name\r\n$6\r\nstream\r\n";
+        let info: Info = from_string(input.to_string()).unwrap();
+        let value: RESPType = from_string(input.to_string()).unwrap();
+        assert_eq!(Info::deserialize(&value).unwrap(), info);
+        assert_eq!(Info::deserialize(value).unwrap(), info);
+
+        let value = to_value(&Pairs(vec![("a", "1"), ("b", "2")])).unwrap();
+        let map = std::collections::HashMap::<String, String>::deserialize(&value).unwrap();
+        assert_eq!(map.len(), 2);
+        assert_eq!(map["b"], "2");
+        let odd = RESPType::Array(Some(vec![RESPType::Integer(1)]));
+        let err = Info::deserialize(odd).unwrap_err();
+        assert!(
+            err.to_string().contains("even number of elements"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn value_errors_match_decoder() {
+        fn check<T: serde::de::DeserializeOwned + std::fmt::Debug>(input: &str) {
+            let value: RESPType = from_string(input.to_string()).unwrap();
+            let err = from_string::<T>(input.to_string()).unwrap_err();
+            assert_eq!(&T::deserialize(&value).unwrap_err(), err.inner());
+            assert_eq!(&T::deserialize(value).unwrap_err(), err.inner());
+        }
+
+        check::<()>("+PONG\r\n");
+        check::<(i64, i64)>("*1\r\n:1\r\n");
+        check::<[u8; 2]>("$3\r\nabc\r\n");
+        check::<std::collections::HashMap<i64, i64>>("*1\r\n:1\r\n");
+    }
+
+    #[test]
+    fn value_unsupported_types() {
+        #[derive(serde::Serialize)]
+        struct Info {
+            length: i64,
+        }
+
+        #[derive(serde::Serialize)]
+        enum Kind {
+            Plain,
+        }
+
+        let mut map = std::collections::HashMap::new();
+        map.insert("a", 1i64);
+        let errors = [
+            (to_value(&Info { length: 1 }), to_vec(&Info { length: 1 })),
+            (to_value(&1.5f32), to_vec(&1.5f32)),
+            (to_value(&1.5f64), to_vec(&1.5f64)),
+            (to_value(&Kind::Plain), to_vec(&Kind::Plain)),
+            (to_value(&map), to_vec(&map)),
+        ];
+        for (value, bytes) in errors.iter() {
+            assert_eq!(value.as_ref().unwrap_err(), bytes.as_ref().unwrap_err());
+        }
+        let err = to_value(&1.5f64).unwrap_err();
+        assert_eq!(err.to_string(), "f64 values are not supported");
+    }
+
+    #[test]
+    fn value_simple_string_with_crlf() {
+        for value in [
+            RESPType::SimpleString(b"OK\r\n:1".to_vec()),
+            RESPType::Error(b"ERR\n".to_vec()),
+        ]
+        .iter()
+        {
+            assert_eq!(to_value(value), Err(Error::InvalidSimpleString));
+            assert_eq!(to_vec(value), Err(Error::InvalidSimpleString));
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use crate::de::DEFAULT_MAX_DEPTH;
+use crate::scan::{build, check_depth, frame_len, next_token, Token};
+use crate::{RESPType, Result};
+
+/// Borrowed view of a value in a raw buffer, with the same variants as
+/// `RESPType`. Array elements are parsed only when they are accessed, so
+/// inspecting a value doesn't allocate.
+///
+/// RESP3 booleans are viewed as integers, see `RESPType`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RESPRef<'a> {
+    SimpleString(&'a [u8]),
+    Error(&'a [u8]),
+    Integer(i64),
+    BulkString(Option<&'a [u8]>),
+    Array(Option<ArrayRef<'a>>),
+}
+
+impl<'a> RESPRef<'a> {
+    /// View the value at the start of `buf`, together with its length in
+    /// bytes. `None` if the value is not complete yet.
+    ///
+    /// The whole value is validated here, nested ones included. Arrays nested
+    /// deeper than `DEFAULT_MAX_DEPTH` levels are rejected with
+    /// `Error::DepthLimit`.
+    pub fn parse(buf: &'a [u8]) -> Result<Option<(RESPRef<'a>, usize)>> {
+        RESPRef::parse_with_max_depth(buf, DEFAULT_MAX_DEPTH)
+    }
+
+    /// Same as `parse`, with arrays nested at most `max_depth` levels deep.
+    /// Limits above `DEFAULT_MAX_DEPTH` need an allocation for the check.
+    pub fn parse_with_max_depth(
+        buf: &'a [u8],
+        max_depth: usize,
+    ) -> Result<Option<(RESPRef<'a>, usize)>> {
+        let len = match frame_len(buf)? {
+            Some(len) => len,
+            None => return Ok(None),
+        };
+        check_depth(&buf[..len], max_depth)?;
+        Ok(Some((RESPRef::from_frame(&buf[..len]), len)))
+    }
+
+    /// Copy the value into a `RESPType`
+    pub fn to_owned(&self) -> RESPType {
+        match self {
+            RESPRef::SimpleString(s) => RESPType::SimpleString(s.to_vec()),
+            RESPRef::Error(s) => RESPType::Error(s.to_vec()),
+            RESPRef::Integer(int) => RESPType::Integer(*int),
+            RESPRef::BulkString(s) => RESPType::BulkString(s.map(<[u8]>::to_vec)),
+            RESPRef::Array(None) => RESPType::Array(None),
+            RESPRef::Array(Some(array)) => build(
+                array.frame,
+                |token| match token {
+                    Token::SimpleString(range) => {
+                        RESPType::SimpleString(array.frame[range].to_vec())
+                    }
+                    Token::Error(range) => RESPType::Error(array.frame[range].to_vec()),
+                    Token::Integer(int) => RESPType::Integer(int),
+                    Token::BulkString(range) => {
+                        RESPType::BulkString(range.map(|range| array.frame[range].to_vec()))
+                    }
+                    Token::Array(_) => RESPType::Array(None),
+                },
+                |items| RESPType::Array(Some(items)),
+            ),
+        }
+    }
+
+    /// `frame` is exactly one value accepted by `frame_len`
+    fn from_frame(frame: &'a [u8]) -> Self {
+        let (token, end) = match next_token(frame, 0) {
+            Ok(Some(token)) => token,
+            _ => panic!("value is not validated by frame_len"),
+        };
+        match token {
+            Token::SimpleString(range) => RESPRef::SimpleString(&frame[range]),
+            Token::Error(range) => RESPRef::Error(&frame[range]),
+            Token::Integer(int) => RESPRef::Integer(int),
+            Token::BulkString(range) => RESPRef::BulkString(range.map(|range| &frame[range])),
+            Token::Array(len) => RESPRef::Array(len.map(|len| ArrayRef {
+                frame,
+                start: end,
+                len,
+            })),
+        }
+    }
+}
+
+/// Elements of an array viewed by `RESPRef`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ArrayRef<'a> {
+    /// Whole array, header included
+    frame: &'a [u8],
+    /// Position of the first element
+    start: usize,
+    len: usize,
+}
+
+impl<'a> ArrayRef<'a> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Element at `index`, elements before it are skipped over
+    pub fn get(&self, index: usize) -> Option<RESPRef<'a>> {
+        self.iter().nth(index)
+    }
+
+    pub fn iter(&self) -> Iter<'a> {
+        Iter {
+            frame: self.frame,
+            pos: self.start,
+            remaining: self.len,
+        }
+    }
+}
+
+impl<'a> IntoIterator for ArrayRef<'a> {
+    type Item = RESPRef<'a>;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+/// Iterator over elements of an `ArrayRef`
+pub struct Iter<'a> {
+    frame: &'a [u8],
+    pos: usize,
+    remaining: usize,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = RESPRef<'a>;
+
+    fn next(&mut self) -> Option<RESPRef<'a>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let rest = &self.frame[self.pos..];
+        let len = match frame_len(rest) {
+            Ok(Some(len)) => len,
+            _ => panic!("value is not validated by frame_len"),
+        };
+        self.pos += len;
+        self.remaining -= 1;
+        Some(RESPRef::from_frame(&rest[..len]))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ser::tests::allocations;
+    use crate::{to_vec, Error};
+
+    #[test]
+    fn view_parse() {
+        let buf = b"*3\r\n$3\r\nGET\r\n*2\r\n:1\r\n-ERR x\r\n$-1\r\n+next\r\n";
+        let (value, len) = RESPRef::parse(buf).unwrap().unwrap();
+        assert_eq!(len, buf.len() - 7);
+        let array = match value {
+            RESPRef::Array(Some(array)) => array,
+            value => panic!("unexpected value {:?}", value),
+        };
+        assert_eq!(array.len(), 3);
+        assert_eq!(array.get(0), Some(RESPRef::BulkString(Some(&b"GET"[..]))));
+        assert_eq!(array.get(2), Some(RESPRef::BulkString(None)));
+        assert_eq!(array.get(3), None);
+        let nested: Vec<RESPRef> = match array.get(1) {
+            Some(RESPRef::Array(Some(nested))) => nested.iter().collect(),
+            value => panic!("unexpected value {:?}", value),
+        };
+        assert_eq!(nested, vec![RESPRef::Integer(1), RESPRef::Error(b"ERR x")]);
+        assert_eq!(
+            RESPRef::parse(&buf[len..]).unwrap().unwrap().0,
+            RESPRef::SimpleString(b"next")
+        );
+    }
+
+    #[test]
+    fn view_incomplete_and_invalid() {
+        let buf = b"*2\r\n$3\r\nGET\r\n$3\r\nke";
+        for end in 0..buf.len() {
+            assert_eq!(RESPRef::parse(&buf[..end]).unwrap(), None);
+        }
+        assert!(RESPRef::parse(b"*2\r\n:1\r\n?\r\n").is_err());
+        assert!(RESPRef::parse(b"$3\r\nabcd\r\n").is_err());
+    }
+
+    #[test]
+    fn view_depth_limit() {
+        let nested = "*1\r\n".repeat(1_000_000) + ":1\r\n";
+        assert!(matches!(
+            RESPRef::parse(nested.as_bytes()),
+            Err(Error::DepthLimit(DEFAULT_MAX_DEPTH))
+        ));
+        let buf = b"*1\r\n*1\r\n:1\r\n";
+        let count = allocations(|| {
+            RESPRef::parse(buf).unwrap().unwrap();
+        });
+        assert_eq!(count, 0);
+        assert!(RESPRef::parse_with_max_depth(buf, 1).is_err());
+        assert!(RESPRef::parse_with_max_depth(buf, 2).unwrap().is_some());
+    }
+
+    #[test]
+    fn view_to_owned() {
+        let value = RESPType::Array(Some(vec![
+            RESPType::SimpleString(b"OK".to_vec()),
+            RESPType::Array(Some(vec![])),
+            RESPType::Array(Some(vec![RESPType::BulkString(Some(b"a\r\nb".to_vec()))])),
+            RESPType::Array(None),
+        ]));
+        let buf = to_vec(&value).unwrap();
+        let (view, _) = RESPRef::parse(&buf).unwrap().unwrap();
+        assert_eq!(view.to_owned(), value);
+        assert_eq!(RESPRef::Integer(5).to_owned(), RESPType::Integer(5));
+    }
+}